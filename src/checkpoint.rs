@@ -0,0 +1,115 @@
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub(crate) struct Checkpoint {
+    entries: Vec<(String, String)>,
+}
+
+impl Checkpoint {
+    pub(crate) fn push(&mut self, key: &str, value: impl Display) -> &mut Self {
+        self.entries.push((key.to_string(), value.to_string()));
+        self
+    }
+    pub(crate) fn get(&self, key: &str) -> Result<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .ok_or(anyhow!("checkpoint is missing '{}'", key))
+    }
+    pub(crate) fn parse<T>(&self, key: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.get(key)?
+            .parse()
+            .map_err(|e| anyhow!("invalid checkpoint value for '{}': {}", key, e))
+    }
+    pub(crate) fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl Display for Checkpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (key, value) in self.entries.iter() {
+            writeln!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Checkpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            entries: s
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| {
+                    line.split_once('=')
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .ok_or(anyhow!("invalid checkpoint line '{}'", line))
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+pub(crate) struct CheckpointFile {
+    path: PathBuf,
+    interval: Duration,
+    last_saved: Instant,
+}
+
+impl CheckpointFile {
+    const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            interval: Self::DEFAULT_INTERVAL,
+            last_saved: Instant::now(),
+        }
+    }
+    pub(crate) fn load(&self) -> Option<Checkpoint> {
+        let contents = std::fs::read_to_string(&self.path).ok()?;
+        match contents.parse() {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(e) => {
+                warn!("ignoring checkpoint {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+    pub(crate) fn save(&mut self, checkpoint: &Checkpoint) -> Result<()> {
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, checkpoint.to_string())?;
+        std::fs::rename(&temp, &self.path)?;
+        self.last_saved = Instant::now();
+        debug!("saved checkpoint {}", self.path.display());
+        Ok(())
+    }
+    pub(crate) fn save_if_due(&mut self, checkpoint: impl FnOnce() -> Checkpoint) -> Result<()> {
+        if self.last_saved.elapsed() >= self.interval {
+            self.save(&checkpoint())?;
+        }
+        Ok(())
+    }
+    pub(crate) fn remove(&self) -> Result<()> {
+        if self.path.exists() {
+            std::fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
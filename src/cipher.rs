@@ -0,0 +1,50 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+const ALPHABET_LEN: u32 = 26;
+
+fn shift_char(c: char, shift: u32) -> char {
+    if c.is_ascii_lowercase() {
+        let offset = (c as u32 - 'a' as u32 + shift % ALPHABET_LEN) % ALPHABET_LEN;
+        char::from_u32('a' as u32 + offset).unwrap()
+    } else {
+        c
+    }
+}
+
+pub(crate) fn decrypt(encrypted: &str, shift: u32) -> String {
+    encrypted.chars().map(|c| shift_char(c, shift)).collect()
+}
+
+pub(crate) fn encrypt(plain: &str, shift: u32) -> String {
+    decrypt(plain, ALPHABET_LEN - shift % ALPHABET_LEN)
+}
+
+pub(crate) fn find_shift(encrypted: &str, keyword: &str) -> Option<u32> {
+    (0..ALPHABET_LEN).find(|&shift| decrypt(encrypted, shift).contains(keyword))
+}
+
+pub(crate) fn letter_frequencies(text: &str) -> Vec<(char, usize)> {
+    let mut counts: Vec<_> = text
+        .chars()
+        .filter(|c| c.is_ascii_lowercase())
+        .fold(HashMap::new(), |mut map: HashMap<char, usize>, c| {
+            *map.entry(c).or_default() += 1;
+            map
+        })
+        .into_iter()
+        .collect();
+    counts.sort_by(|a, b| match b.1.cmp(&a.1) {
+        Ordering::Equal => a.0.cmp(&b.0),
+        c => c,
+    });
+    counts
+}
+
+pub(crate) fn checksum(text: &str, len: usize) -> String {
+    letter_frequencies(text)
+        .into_iter()
+        .take(len)
+        .map(|(c, _)| c)
+        .collect()
+}
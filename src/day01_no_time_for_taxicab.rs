@@ -1,4 +1,6 @@
-use std::collections::HashSet;
+use crate::grid::{Heading, Point, Turn, Walker};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 pub(crate) fn run() {
@@ -6,6 +8,9 @@ pub(crate) fn run() {
     let _input = "R8, R4, R4, R8";
     let _input = _get_input();
 
+    let draw_map = false;
+    let svg_output: Option<&str> = None;
+
     let instructions: Vec<Instruction> = _input
         .split_whitespace()
         .map(|line| line.trim_matches(',').parse().unwrap())
@@ -16,26 +21,29 @@ pub(crate) fn run() {
         person.walk(instruction);
     }
     println!(
-        "last destination is {} blocks away",
-        person.distance_from_start()
+        "last destination is {} blocks away after walking {} blocks",
+        person.distance_from_start(),
+        person.path().len() - 1
     );
 
-    let mut person = Person::new();
-    let mut locations_visited: HashSet<Coord> = Default::default();
-    for instruction in instructions.iter() {
-        let path = person.walk(instruction);
-        let first_location_visited_twice =
-            path.iter().filter(|c| locations_visited.contains(c)).next();
-        if let Some(location) = first_location_visited_twice {
-            println!(
-                "first location visited twice is {} blocks away",
-                location.get_manhattan_distance(&Default::default())
-            );
-            return;
-        }
-        locations_visited.extend(path.into_iter());
+    if let Some(location) = person.first_location_visited_twice() {
+        println!(
+            "first location visited twice is {} blocks away",
+            location.manhattan_distance(&Point::default())
+        );
+    } else {
+        println!("no locations were visited twice");
+    }
+
+    let map = RouteMap::new(person.path());
+    println!("the route crosses itself {} times", map.crossings.len());
+    if draw_map {
+        println!("{}", map);
+    }
+    if let Some(file_name) = svg_output {
+        std::fs::write(file_name, map.to_svg()).expect("cannot write svg file");
+        println!("route map written to {}", file_name);
     }
-    println!("no locations were visited twice");
 }
 
 #[derive(Debug)]
@@ -44,40 +52,133 @@ enum Error {
 }
 
 struct Person {
-    facing_direction: FacingDirection,
-    position: Coord,
+    walker: Walker,
 }
 
 impl Person {
     pub(crate) fn new() -> Self {
         Self {
-            facing_direction: FacingDirection::North,
-            position: Default::default(),
+            walker: Walker::unbounded(Point::default(), Heading::North),
         }
     }
-    pub(crate) fn walk(&mut self, instruction: &Instruction) -> Vec<Coord> {
-        self.facing_direction = self.facing_direction.turn(&instruction.turn_direction);
-        let path = self
-            .position
-            .get_next(&self.facing_direction, instruction.walk_blocks);
-        if let Some(last) = path.last() {
-            self.position = last.clone();
+    pub(crate) fn walk(&mut self, instruction: &Instruction) {
+        self.walker.turn(instruction.turn);
+        self.walker.forward(instruction.walk_blocks);
+    }
+    pub(crate) fn distance_from_start(&self) -> u64 {
+        self.walker
+            .position()
+            .manhattan_distance(&self.walker.start())
+    }
+    pub(crate) fn first_location_visited_twice(&self) -> Option<Point> {
+        self.walker.first_revisit()
+    }
+    pub(crate) fn path(&self) -> &[Point] {
+        self.walker.path()
+    }
+}
+
+struct RouteMap<'a> {
+    path: &'a [Point],
+    crossings: Vec<Point>,
+    min: Point,
+    max: Point,
+}
+
+impl<'a> RouteMap<'a> {
+    pub(crate) fn new(path: &'a [Point]) -> Self {
+        let mut visits: HashMap<Point, usize> = Default::default();
+        let mut crossings = vec![];
+        for point in path.iter() {
+            let count = visits.entry(*point).or_default();
+            *count += 1;
+            if *count == 2 {
+                crossings.push(*point);
+            }
+        }
+        Self {
+            path,
+            crossings,
+            min: Point::new(
+                path.iter().map(|p| p.x).min().unwrap_or_default(),
+                path.iter().map(|p| p.y).min().unwrap_or_default(),
+            ),
+            max: Point::new(
+                path.iter().map(|p| p.x).max().unwrap_or_default(),
+                path.iter().map(|p| p.y).max().unwrap_or_default(),
+            ),
         }
-        path
     }
-    pub(crate) fn distance_from_start(&self) -> i32 {
-        self.position.get_manhattan_distance(&Default::default())
+    fn start(&self) -> Point {
+        self.path.first().copied().unwrap_or_default()
+    }
+    fn end(&self) -> Point {
+        self.path.last().copied().unwrap_or_default()
+    }
+    pub(crate) fn to_svg(&self) -> String {
+        const MARGIN: i64 = 2;
+        let circle = |point: &Point, radius: f32, color: &str| {
+            format!(
+                "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>\n",
+                point.x, point.y, radius, color
+            )
+        };
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+            self.min.x - MARGIN,
+            self.min.y - MARGIN,
+            self.max.x - self.min.x + 2 * MARGIN,
+            self.max.y - self.min.y + 2 * MARGIN
+        );
+        svg.push_str(&format!(
+            "  <polyline fill=\"none\" stroke=\"black\" stroke-width=\"0.3\" points=\"{}\"/>\n",
+            self.path
+                .iter()
+                .map(|p| format!("{},{}", p.x, p.y))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+        for (i, crossing) in self.crossings.iter().enumerate() {
+            if i == 0 {
+                svg.push_str(&circle(crossing, 1.5, "orange"));
+            } else {
+                svg.push_str(&circle(crossing, 0.8, "blue"));
+            }
+        }
+        svg.push_str(&circle(&self.start(), 1.5, "green"));
+        svg.push_str(&circle(&self.end(), 1.5, "red"));
+        svg.push_str("</svg>\n");
+        svg
     }
 }
 
-enum TurnDirection {
-    Left,
-    Right,
+impl<'a> Display for RouteMap<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut grid = vec![
+            vec!['.'; (self.max.x - self.min.x + 1) as usize];
+            (self.max.y - self.min.y + 1) as usize
+        ];
+        let mut set = |point: &Point, c: char| {
+            grid[(point.y - self.min.y) as usize][(point.x - self.min.x) as usize] = c;
+        };
+        for point in self.path.iter() {
+            set(point, '#');
+        }
+        for point in self.crossings.iter() {
+            set(point, 'X');
+        }
+        set(&self.start(), 'S');
+        set(&self.end(), 'E');
+        for row in grid.into_iter() {
+            writeln!(f, "{}", row.into_iter().collect::<String>())?;
+        }
+        Ok(())
+    }
 }
 
 struct Instruction {
-    turn_direction: TurnDirection,
-    walk_blocks: i32,
+    turn: Turn,
+    walk_blocks: usize,
 }
 
 impl FromStr for Instruction {
@@ -85,15 +186,13 @@ impl FromStr for Instruction {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(Self {
-            turn_direction: match s
+            turn: s
                 .chars()
                 .nth(0)
-                .ok_or(Error::ParseInstructionError("cannot get direction"))?
-            {
-                'L' => TurnDirection::Left,
-                'R' => TurnDirection::Right,
-                _ => return Err(Error::ParseInstructionError("invalid direction")),
-            },
+                .ok_or(Error::ParseInstructionError("cannot get direction"))
+                .and_then(|c| {
+                    Turn::try_from(c).or(Err(Error::ParseInstructionError("invalid direction")))
+                })?,
             walk_blocks: s[1..]
                 .parse()
                 .or(Err(Error::ParseInstructionError("invalid walk_blocks")))?,
@@ -101,69 +200,6 @@ impl FromStr for Instruction {
     }
 }
 
-enum FacingDirection {
-    North,
-    South,
-    East,
-    West,
-}
-
-impl FacingDirection {
-    pub(crate) fn turn(&self, direction: &TurnDirection) -> Self {
-        match self {
-            FacingDirection::North => match direction {
-                TurnDirection::Left => Self::West,
-                TurnDirection::Right => Self::East,
-            },
-            FacingDirection::South => match direction {
-                TurnDirection::Left => Self::East,
-                TurnDirection::Right => Self::West,
-            },
-            FacingDirection::East => match direction {
-                TurnDirection::Left => Self::North,
-                TurnDirection::Right => Self::South,
-            },
-            FacingDirection::West => match direction {
-                TurnDirection::Left => Self::South,
-                TurnDirection::Right => Self::North,
-            },
-        }
-    }
-}
-
-#[derive(Default, Clone, Hash, Eq, PartialEq)]
-struct Coord {
-    x: i32,
-    y: i32,
-}
-
-impl Coord {
-    fn get_axis(&mut self, direction: &FacingDirection) -> &mut i32 {
-        match direction {
-            FacingDirection::North | FacingDirection::South => &mut self.y,
-            FacingDirection::East | FacingDirection::West => &mut self.x,
-        }
-    }
-    pub(crate) fn get_next(&self, direction: &FacingDirection, blocks: i32) -> Vec<Self> {
-        let mut result = self.clone();
-        let sign: i32 = match direction {
-            FacingDirection::North | FacingDirection::East => 1,
-            FacingDirection::South | FacingDirection::West => -1,
-        };
-
-        let mut results: Vec<Self> = Default::default();
-        for _ in 0..blocks {
-            *result.get_axis(direction) += sign;
-            results.push(result.clone());
-        }
-
-        results
-    }
-    pub(crate) fn get_manhattan_distance(&self, other: &Self) -> i32 {
-        (self.x - other.x).abs() + (self.y - other.y).abs()
-    }
-}
-
 fn _get_input() -> &'static str {
     "R4, R1, L2, R1, L1, L1, R1, L5, R1, R5, L2, R3, L3, L4, R4, R4, R3, L5, L1, R5, R3, L4, R1, R5, L1, R3, L2, R3, R1, L4, L1, R1, L1, L5, R1, L2, R2, L3, L5, R1, R5, L1, R188, L3, R2, R52, R5, L3, R79, L1, R5, R186, R2, R1, L3, L5, L2, R2, R4, R5, R5, L5, L4, R5, R3, L4, R4, L4, L4, R5, L4, L3, L1, L4, R1, R2, L5, R3, L4, R3, L3, L5, R1, R1, L3, R2, R1, R2, R2, L4, R5, R1, R3, R2, L2, L2, L1, R2, L1, L3, R5, R1, R4, R5, R2, R2, R4, R4, R1, L3, R4, L2, R2, R1, R3, L5, R5, R2, R5, L1, R2, R4, L1, R5, L3, L3, R1, L4, R2, L2, R1, L1, R4, R3, L2, L3, R3, L2, R1, L4, R5, L1, R5, L2, L1, L5, L2, L5, L2, L4, L2, R3"
 }
@@ -1,3 +1,18 @@
+use crate::grid::{Bounds, Heading, Point};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const SIMPLE_LAYOUT: &str = "123
+456
+789";
+
+const FANCY_LAYOUT: &str = "  1
+ 234
+56789
+ ABC
+  D";
+
 pub(crate) fn run() {
     let _input = "ULL
 RRDDD
@@ -7,11 +22,14 @@ UUUUD";
 
     let use_fancy = true;
 
-    let code = if use_fancy {
-        obtain_code(&mut FancyKeypad::new(), _input)
+    let layout: KeypadLayout = if use_fancy {
+        FANCY_LAYOUT
     } else {
-        obtain_code(&mut SimpleKeypad::new(), _input)
-    };
+        SIMPLE_LAYOUT
+    }
+    .parse()
+    .unwrap();
+    let code = obtain_code(&mut Keypad::new(layout, '5').unwrap(), _input).unwrap();
 
     println!(
         "bathroom code for {} keypad is {}",
@@ -20,182 +38,108 @@ UUUUD";
     );
 }
 
-fn obtain_code<T>(keypad: &mut dyn Keypad<ValueType = T>, directions: &str) -> String {
-    let directions: Vec<Vec<Direction>> = directions
+fn obtain_code(keypad: &mut Keypad, directions: &str) -> Result<String> {
+    let directions: Vec<Vec<Heading>> = directions
         .split('\n')
-        .map(|line| line.chars().map(|c| c.into()).collect())
-        .collect();
+        .map(|line| line.chars().map(|c| c.try_into()).collect())
+        .collect::<Result<_>>()?;
     for directions in directions.iter() {
         keypad.go_to_next(directions);
     }
-    keypad.get_code()
+    Ok(keypad.get_code().to_string())
 }
 
-trait Keypad {
-    type ValueType;
-    fn get_code(&self) -> String;
-    fn apply_direction(&mut self, direction: &Direction);
-    fn get_value(&self) -> Self::ValueType;
-    fn push_value(&mut self, value: Self::ValueType);
-    fn go_to_next(&mut self, directions: &[Direction]) {
-        for direction in directions {
-            self.apply_direction(direction);
-        }
-        let value = self.get_value();
-        self.push_value(value);
+struct KeypadLayout {
+    keys: HashMap<Point, char>,
+}
+
+impl KeypadLayout {
+    pub(crate) fn find(&self, key: char) -> Option<Point> {
+        self.keys
+            .iter()
+            .find(|(_, &k)| k == key)
+            .map(|(point, _)| *point)
     }
 }
 
-struct FancyKeypad {
-    pressed: Vec<char>,
-    current: Coord,
+impl Bounds for KeypadLayout {
+    fn contains(&self, point: &Point) -> bool {
+        self.keys.contains_key(point)
+    }
 }
 
-impl Keypad for FancyKeypad {
-    type ValueType = char;
+impl FromStr for KeypadLayout {
+    type Err = anyhow::Error;
 
-    fn get_code(&self) -> String {
-        self.pressed.iter().copied().collect()
-    }
-    fn apply_direction(&mut self, direction: &Direction) {
-        let (target, other) = match direction {
-            Direction::Up | Direction::Down => (&mut self.current.row, self.current.col),
-            Direction::Left | Direction::Right => (&mut self.current.col, self.current.row),
-        };
-        let range = match other {
-            0 | 4 => 2..=2,
-            1 | 3 => 1..=3,
-            2 => 0..=4,
-            _ => panic!("current coordinate out of range"),
-        };
-        match direction {
-            Direction::Down | Direction::Right => {
-                if *target < *range.end() {
-                    *target += 1;
-                }
-            }
-            Direction::Up | Direction::Left => {
-                if *target > *range.start() {
-                    *target -= 1;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys: HashMap<Point, char> = Default::default();
+        for (y, line) in s.lines().enumerate() {
+            for (x, c) in line.chars().enumerate().filter(|(_, c)| !c.is_whitespace()) {
+                if keys.values().any(|&k| k == c) {
+                    return Err(anyhow!("duplicate key '{}' in keypad layout", c));
                 }
+                keys.insert(Point::new(x as i64, y as i64), c);
             }
         }
-        println!(
-            "  >moved {:?} to {} ({:?})",
-            direction,
-            self.get_value(),
-            self.current
-        );
-    }
-    fn get_value(&self) -> char {
-        match self.current.row {
-            0 => '1',
-            1 => char::from_digit(1 + self.current.col as u32, 10).unwrap(),
-            2 => char::from_digit(5 + self.current.col as u32, 10).unwrap(),
-            3 => ('A' as u8 + self.current.col as u8 - 1) as char,
-            4 => 'D',
-            _ => panic!("out of bounds get_value for fancy keypad"),
+        if keys.is_empty() {
+            return Err(anyhow!("keypad layout has no keys"));
         }
-    }
-    fn push_value(&mut self, value: char) {
-        self.pressed.push(value);
-        println!("pressed {}", value);
+        Ok(Self { keys })
     }
 }
 
-impl FancyKeypad {
-    pub(crate) fn new() -> Self {
-        Self {
-            pressed: vec![],
-            current: Coord { row: 2, col: 0 },
-        }
-    }
+struct Keypad {
+    pressed: String,
+    layout: KeypadLayout,
+    position: Point,
 }
 
-impl Keypad for SimpleKeypad {
-    type ValueType = u8;
-
-    fn get_code(&self) -> String {
-        self.pressed
-            .iter()
-            .rev()
-            .enumerate()
-            .map(|(i, &val)| 10u64.pow(i as u32) * val as u64)
-            .sum::<u64>()
-            .to_string()
+impl Keypad {
+    pub(crate) fn new(layout: KeypadLayout, start_key: char) -> Result<Self> {
+        let start = layout
+            .find(start_key)
+            .ok_or(anyhow!("start key '{}' is not on the keypad", start_key))?;
+        Ok(Self {
+            pressed: String::new(),
+            layout,
+            position: start,
+        })
     }
-    fn apply_direction(&mut self, direction: &Direction) {
-        let target = match direction {
-            Direction::Up | Direction::Down => &mut self.current.row,
-            Direction::Left | Direction::Right => &mut self.current.col,
-        };
-        match direction {
-            Direction::Up | Direction::Right => {
-                if *target < Self::WIDTH - 1 {
-                    *target += 1;
-                }
-            }
-            Direction::Down | Direction::Left => {
-                if *target > 0 {
-                    *target -= 1;
-                }
-            }
+    pub(crate) fn get_code(&self) -> &str {
+        &self.pressed
+    }
+    fn apply_direction(&mut self, direction: &Heading) {
+        let next = self.position.moved(*direction, 1);
+        if self.layout.contains(&next) {
+            self.position = next;
         }
-        println!("  >moved {:?} to {}", direction, self.get_value());
+        println!(
+            "  >moved {:?} to {} ({:?})",
+            direction,
+            self.get_value(),
+            self.position
+        );
     }
-    #[inline]
-    fn get_value(&self) -> u8 {
-        let coord = &self.current;
-        (1 + 3 * (2 - coord.row) + coord.col) as u8
+    fn get_value(&self) -> char {
+        *self
+            .layout
+            .keys
+            .get(&self.position)
+            .expect("cursor left the keypad")
     }
-    fn push_value(&mut self, value: u8) {
+    fn push_value(&mut self, value: char) {
         self.pressed.push(value);
         println!("pressed {}", value);
     }
-}
-
-struct SimpleKeypad {
-    pressed: Vec<u8>,
-    current: Coord,
-}
-
-impl SimpleKeypad {
-    const WIDTH: usize = 3;
-
-    pub(crate) fn new() -> Self {
-        Self {
-            pressed: vec![],
-            current: Coord { row: 1, col: 1 },
-        }
-    }
-}
-
-#[derive(Copy, Clone, Debug)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl From<char> for Direction {
-    fn from(c: char) -> Self {
-        match c {
-            'U' => Self::Up,
-            'D' => Self::Down,
-            'L' => Self::Left,
-            'R' => Self::Right,
-            _ => panic!("out of bounds char"),
+    pub(crate) fn go_to_next(&mut self, directions: &[Heading]) {
+        for direction in directions {
+            self.apply_direction(direction);
         }
+        let value = self.get_value();
+        self.push_value(value);
     }
 }
 
-#[derive(Debug, Hash, Eq, PartialEq)]
-struct Coord {
-    row: usize,
-    col: usize,
-}
-
 fn _get_input() -> &'static str {
     "RRLLRLLRULLRUUUDRDLDDLLLDDDDDUUURRRRUUDLRULURRRDRUDRUUDDRUDLLLRLDDDUDRDDRRLLLLRLRLULUURDRURRUULDRRDUDURRUURURDLURULLDUDRDLUUUUDDURRLLLUDLDLRDRRRDULLDLDULLDRLDLDURDLRRULLDDLDRLLLUDDLLRDURULLDDDDDUURURLRLRRDUURUULRLLLULLRLULLUUDRRLLDURLDDDDULUUDLUDDDULRLDURDDRUUDRRUUURLLLULURUDRULDRDUDUDRRDDULRURLLRRLRRLLDLULURDRDRULDRDRURUDLLRRDUUULDDDUURDLULDLRLLURRURLLUDURDDRUDRDLLLLDLRLDLDDRDRRDUUULLUULRRDLURLDULLDLDUUUULLLDRURLRULLULRLULUURLLRDDRULDULRLDRRURLURUDLRRRLUDLDUULULLURLDDUDDLLUDRUDRLDUDURRRRLRUUURLUDDUDURDUDDDLLRLRDDURDRUUDUDRULURLRLDRULDRRLRLDDDRDDDRLDUDRLULDLUDLRLRRRLRDULDDLRRDDLDDULDLLDU
 RULLUDDUDLULRRDLLDRUDLLLDURLLLURDURLRDRRDLRDRDLLURRULUULUDUDDLLRRULLURDRLDURDLDDUURLUURLDLDLRLDRLRUULDRLRLDRLRLUDULURDULLLDRUDULDURURRRUDURDUDLRDRRURULRRLRLRRRRRRDRUDLDRULDRUDLRDLRRUDULDLRLURRRLLDRULULRUDULRLULLRLULDRUDUULLRUULDULDUDDUUULLLDRDDRRDLURUUDRRLRRRDLRRLULLLLDLRUULDLLULURUURURDRURLLDUDRRURRURRUUDDRRDDRRRRUDULULRLUULRRDDRDDLLUDLDLULLRLDRLLUULDURLDRULDDUDRUUUURRLDDUDRUURUDLLDLDLURDLULDRLLLULLLUDLLDLD
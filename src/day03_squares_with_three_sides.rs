@@ -1,69 +1,176 @@
-use std::str::FromStr;
+use anyhow::{anyhow, Result};
+use std::collections::VecDeque;
+use std::io::BufRead;
 
 pub(crate) fn run() {
     let input = get_input();
+    let sides = 3;
 
-    let candidates: Vec<TriangleCandidate> = input
-        .split('\n')
-        .map(|line| line.parse().unwrap())
-        .collect();
-    println!(
-        "there are {} triangles",
-        candidates.iter().filter(|t| t.is_triangle()).count()
-    );
+    let count = count_valid(CandidateReader::new(
+        input.as_bytes(),
+        sides,
+        Grouping::Rows,
+    ))
+    .unwrap();
+    println!("there are {} triangles", count);
 
-    let candidates = get_candidates_vertically(input).unwrap();
-    println!(
-        "grouping vertically, there are {} triangles",
-        candidates.iter().filter(|t| t.is_triangle()).count()
-    );
+    let count = count_valid(CandidateReader::new(
+        input.as_bytes(),
+        sides,
+        Grouping::Columns,
+    ))
+    .unwrap();
+    println!("grouping vertically, there are {} triangles", count);
 }
 
-fn get_candidates_vertically(input: &str) -> Result<Vec<TriangleCandidate>, ()> {
-    let mut results: Vec<TriangleCandidate> =
-        Vec::with_capacity(3 * (input.chars().filter(|&c| c == '\n').count() + 1));
+fn count_valid(mut candidates: impl Iterator<Item = Result<PolygonCandidate>>) -> Result<usize> {
+    candidates.try_fold(0, |count, candidate| {
+        Ok(count + if candidate?.is_polygon() { 1 } else { 0 })
+    })
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Grouping {
+    Rows,
+    Columns,
+}
 
-    let mut current: Vec<u32> = Default::default();
-    for i in 0..3 {
-        for line in input.split('\n') {
-            let next: u32 = line
-                .trim()
+struct CandidateReader<R: BufRead> {
+    reader: R,
+    sides: usize,
+    grouping: Grouping,
+    line_number: usize,
+    buffer: String,
+    row_values: Vec<u64>,
+    pending: VecDeque<PolygonCandidate>,
+}
+
+impl<R: BufRead> CandidateReader<R> {
+    pub(crate) fn new(reader: R, sides: usize, grouping: Grouping) -> Self {
+        Self {
+            reader,
+            sides,
+            grouping,
+            line_number: 0,
+            buffer: String::new(),
+            row_values: Vec::with_capacity(sides),
+            pending: VecDeque::new(),
+        }
+    }
+    fn next_line(&mut self) -> Result<Option<Vec<u64>>> {
+        loop {
+            self.buffer.clear();
+            if self.reader.read_line(&mut self.buffer)? == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            if self.buffer.trim().is_empty() {
+                continue;
+            }
+            return self
+                .buffer
                 .split_whitespace()
-                .nth(i)
-                .ok_or(())?
-                .parse()
-                .or(Err(()))?;
-            current.push(next);
-            if current.len() == 3 {
-                results.push(TriangleCandidate::new(current[0], current[1], current[2]));
-                current = Default::default();
+                .map(|value| {
+                    value.parse().map_err(|_| {
+                        anyhow!("invalid value '{}' on line {}", value, self.line_number)
+                    })
+                })
+                .collect::<Result<_>>()
+                .map(Some);
+        }
+    }
+    fn fill_by_rows(&mut self) -> Result<()> {
+        while let Some(values) = self.next_line()? {
+            for value in values {
+                self.row_values.push(value);
+                if self.row_values.len() == self.sides {
+                    let sides =
+                        std::mem::replace(&mut self.row_values, Vec::with_capacity(self.sides));
+                    self.pending.push_back(PolygonCandidate::new(sides));
+                }
             }
+            if !self.pending.is_empty() {
+                return Ok(());
+            }
+        }
+        let left_over = std::mem::take(&mut self.row_values);
+        if !left_over.is_empty() {
+            return Err(anyhow!(
+                "{} values left over at end of input, expected groups of {}",
+                left_over.len(),
+                self.sides
+            ));
         }
+        Ok(())
+    }
+    fn fill_by_columns(&mut self) -> Result<()> {
+        let mut columns: Vec<Vec<u64>> = vec![];
+        for i in 0..self.sides {
+            let values = match self.next_line()? {
+                Some(values) => values,
+                None if i == 0 => return Ok(()),
+                None => {
+                    return Err(anyhow!(
+                        "input ended after {} of {} lines in a column group",
+                        i,
+                        self.sides
+                    ))
+                }
+            };
+            if i == 0 {
+                columns = vec![Vec::with_capacity(self.sides); values.len()];
+            } else if values.len() != columns.len() {
+                return Err(anyhow!(
+                    "line {} has {} columns, expected {}",
+                    self.line_number,
+                    values.len(),
+                    columns.len()
+                ));
+            }
+            for (column, value) in columns.iter_mut().zip(values) {
+                column.push(value);
+            }
+        }
+        self.pending
+            .extend(columns.into_iter().map(PolygonCandidate::new));
+        Ok(())
     }
-
-    Ok(results)
 }
 
-struct TriangleCandidate([u32; 3]);
+impl<R: BufRead> Iterator for CandidateReader<R> {
+    type Item = Result<PolygonCandidate>;
 
-impl TriangleCandidate {
-    pub(crate) fn new(item1: u32, item2: u32, item3: u32) -> Self {
-        let mut result = Self([item1, item2, item3]);
-        result.0.sort();
-        result
-    }
-    pub(crate) fn is_triangle(&self) -> bool {
-        self.0[0] + self.0[1] > self.0[2]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() {
+            let filled = match self.grouping {
+                Grouping::Rows => self.fill_by_rows(),
+                Grouping::Columns => self.fill_by_columns(),
+            };
+            if let Err(e) = filled {
+                return Some(Err(e));
+            }
+        }
+        self.pending.pop_front().map(Ok)
     }
 }
 
-impl FromStr for TriangleCandidate {
-    type Err = ();
+#[derive(Debug)]
+struct PolygonCandidate(Vec<u64>);
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.trim().split_whitespace();
-        let mut get_next = || parts.next().ok_or(())?.parse::<u32>().or(Err(()));
-        Ok(Self::new(get_next()?, get_next()?, get_next()?))
+impl PolygonCandidate {
+    pub(crate) fn new(mut sides: Vec<u64>) -> Self {
+        sides.sort();
+        Self(sides)
+    }
+    pub(crate) fn is_polygon(&self) -> bool {
+        let (longest, rest) = match self.0.split_last() {
+            Some(split) if self.0.len() >= 3 => split,
+            _ => return false,
+        };
+        rest.iter()
+            .try_fold(0u64, |sum, &side| sum.checked_add(side))
+            .map(|sum| sum > *longest)
+            .unwrap_or(true)
     }
 }
 
@@ -1,5 +1,9 @@
-use std::cmp::Ordering;
-use std::collections::HashMap;
+use crate::cipher;
+use anyhow::{anyhow, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 pub(crate) fn run() {
     let _input = "\
@@ -9,7 +13,10 @@ not-a-real-room-404[oarel]
 totally-real-room-200[decoy]";
     let _input = _get_input();
 
-    let rooms: Vec<Room> = _input.split('\n').map(|line| line.into()).collect();
+    let rooms: Vec<Room> = _input
+        .split('\n')
+        .map(|line| line.parse().unwrap())
+        .collect();
 
     println!(
         "sum real ids: {}",
@@ -20,18 +27,27 @@ totally-real-room-200[decoy]";
             .sum::<u32>()
     );
 
-    let room: Room = "qzmt-zixmtkozy-ivhz-343[abcde]".into();
+    let room: Room = "qzmt-zixmtkozy-ivhz-343[abcde]".parse().unwrap();
     assert_eq!(room.get_real_name().as_str(), "very encrypted name");
 
+    let mut generator = RoomGenerator::new(2016);
+    for _ in 0..100 {
+        let generated = generator.generate();
+        let line = generated.room.to_string();
+        let parsed: Room = line.parse().unwrap();
+        assert!(parsed.is_real(), "generated room {} is not real", line);
+        assert_eq!(parsed.get_real_name(), generated.real_name);
+    }
+
     println!("rooms:");
     for room in rooms.iter() {
         room.print();
     }
 
+    let keyword = "northpole";
     let north_pole = rooms
         .iter()
-        .filter(|r| r.get_real_name().contains("northpole"))
-        .next()
+        .find(|r| cipher::find_shift(&r.encrypted_name, keyword) == Some(r.sector_id % 26))
         .unwrap();
     println!("north pole room:");
     north_pole.print();
@@ -39,66 +55,102 @@ totally-real-room-200[decoy]";
 
 #[derive(Debug)]
 struct Room {
-    most_common_letters: [char; 5],
+    checksum: String,
     sector_id: u32,
-    encrypted_name: &'static str,
+    encrypted_name: String,
 }
 
 impl Room {
-    fn get_counts(&self) -> HashMap<char, usize> {
-        self.encrypted_name
-            .chars()
-            .filter(|&c| c != '-')
-            .fold(HashMap::new(), |mut map, c| {
-                *map.entry(c).or_default() += 1;
-                map
-            })
-    }
+    const CHECKSUM_LEN: usize = 5;
+
     pub(crate) fn print(&self) {
         println!("[{}] {}", self.sector_id, self.get_real_name());
     }
     pub(crate) fn is_real(&self) -> bool {
-        let mut counts: Vec<_> = self.get_counts().into_iter().collect();
-        counts.sort_by(|&a, &b| {
-            let c = b.1.cmp(&a.1);
-            if let Ordering::Equal = c {
-                a.0.cmp(&b.0)
-            } else {
-                c
-            }
-        });
-
-        self.most_common_letters
-            .iter()
-            .enumerate()
-            .all(|(i, c)| *c == counts[i].0)
+        cipher::checksum(&self.encrypted_name, Self::CHECKSUM_LEN) == self.checksum
     }
     pub(crate) fn get_real_name(&self) -> String {
-        let map_char = |c: char| {
-            let mut c = c as u8 - 'a' as u8;
-            c = ((c as u32 + self.sector_id) % 26) as u8;
-            (c + 'a' as u8) as char
-        };
-        self.encrypted_name
-            .split('-')
-            .map(|encrypted| encrypted.chars().map(|c| map_char(c)).collect::<String>())
-            .collect::<Vec<String>>()
-            .join(" ")
+        cipher::decrypt(&self.encrypted_name, self.sector_id).replace('-', " ")
+    }
+}
+
+impl FromStr for Room {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rest, checksum) = s
+            .trim()
+            .strip_suffix(']')
+            .and_then(|s| s.split_once('['))
+            .ok_or(anyhow!("missing checksum in room '{}'", s))?;
+        let (encrypted_name, id) = rest
+            .rsplit_once('-')
+            .ok_or(anyhow!("missing sector id in room '{}'", s))?;
+        Ok(Self {
+            checksum: checksum.to_string(),
+            sector_id: id.parse()?,
+            encrypted_name: encrypted_name.to_string(),
+        })
+    }
+}
+
+impl Display for Room {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}[{}]",
+            self.encrypted_name, self.sector_id, self.checksum
+        )
     }
 }
 
-impl From<&'static str> for Room {
-    fn from(s: &'static str) -> Self {
-        let len = s.len();
-        let common_letters: Vec<char> = (&s[len - 6..len - 1]).chars().collect();
-        let s = &s[..len - 7];
-        let id = s.split('-').last().unwrap();
-        let s = &s[..s.len() - id.len() - 1];
+struct GeneratedRoom {
+    room: Room,
+    real_name: String,
+}
+
+struct RoomGenerator {
+    rng: StdRng,
+}
+
+impl RoomGenerator {
+    pub(crate) fn new(seed: u64) -> Self {
         Self {
-            most_common_letters: common_letters.try_into().unwrap(),
-            sector_id: id.parse().unwrap(),
-            encrypted_name: s,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+    fn random_word(&mut self) -> String {
+        let len = self.rng.gen_range(3..=10);
+        (0..len).map(|_| self.rng.gen_range('a'..='z')).collect()
+    }
+    pub(crate) fn generate(&mut self) -> GeneratedRoom {
+        loop {
+            let words = self.rng.gen_range(1..=4);
+            let name: Vec<String> = (0..words).map(|_| self.random_word()).collect();
+            let sector_id = self.rng.gen_range(100..1000);
+            if let Ok(room) = Self::encrypt_room(&name.join("-"), sector_id) {
+                return GeneratedRoom {
+                    room,
+                    real_name: name.join(" "),
+                };
+            }
+        }
+    }
+    pub(crate) fn encrypt_room(real_name: &str, sector_id: u32) -> Result<Room> {
+        let encrypted_name = cipher::encrypt(&real_name.replace(' ', "-"), sector_id);
+        let checksum = cipher::checksum(&encrypted_name, Room::CHECKSUM_LEN);
+        if checksum.len() < Room::CHECKSUM_LEN {
+            return Err(anyhow!(
+                "'{}' has fewer than {} distinct letters",
+                real_name,
+                Room::CHECKSUM_LEN
+            ));
         }
+        Ok(Room {
+            checksum,
+            sector_id,
+            encrypted_name,
+        })
     }
 }
 
@@ -1,96 +1,211 @@
+use crate::checkpoint::{Checkpoint, CheckpointFile};
+use crate::md5_mining::{hex_char, leading_zero_nibbles, nibble, Digest, Miner};
+use anyhow::{anyhow, Result};
+use log::warn;
+use rand::Rng;
+use std::io::{IsTerminal, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
 pub(crate) fn run() {
     let _input = "abc";
     let _input = _get_input();
 
+    let cinematic = true;
+    let checkpoint_file: Option<&str> = None;
+    // let checkpoint_file: Option<&str> = Some("day05_checkpoint.txt");
+
     // let mut hacker = Hacker::new(_input);
     let mut hacker = HackerV2::new(_input);
+    if let Some(path) = checkpoint_file {
+        hacker = hacker.with_checkpoint(CheckpointFile::new(path));
+    }
+
+    let password = if cinematic && std::io::stdout().is_terminal() {
+        decrypt_cinematic(hacker)
+    } else {
+        let n = hacker.password.len();
+        while !hacker.is_resolved() {
+            hacker.get_next();
+            println!("{} %", hacker.resolved_count() * 100 / n);
+        }
+        hacker.finish()
+    };
+    println!("password is {}", password);
+}
 
-    let n = 8;
-    for i in 0..n {
-        hacker.get_next();
-        println!("{} %", (i + 1) * 100 / n);
+fn decrypt_cinematic(mut hacker: HackerV2) -> String {
+    const FRAME_DELAY: Duration = Duration::from_millis(50);
+
+    let (sender, receiver) = mpsc::channel();
+    let miner = thread::spawn(move || {
+        sender.send(hacker.password).unwrap();
+        while !hacker.is_resolved() {
+            hacker.get_next();
+            sender.send(hacker.password).unwrap();
+        }
+        hacker.finish()
+    });
+
+    let mut rng = rand::thread_rng();
+    let mut stdout = std::io::stdout();
+    let mut password = [None; 8];
+    print!("\x1b[?25l");
+    loop {
+        match receiver.recv_timeout(FRAME_DELAY) {
+            Ok(next) => password = next,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        let frame: String = password
+            .iter()
+            .map(|c| match c {
+                Some(c) => format!("\x1b[1;32m{}\x1b[0m", c),
+                None => hex_char(rng.gen_range(0..16)).to_string(),
+            })
+            .collect();
+        print!("\rdecrypting: {}", frame);
+        stdout.flush().unwrap();
     }
-    println!("password is {}", hacker.get_password());
+    println!("\x1b[?25h");
+
+    miner.join().unwrap()
 }
 
-struct Hacker<'a> {
-    door_id: &'a str,
-    nonce: usize,
+type DigestFilter = fn(&Digest) -> bool;
+
+struct Hacker {
+    miner: Miner<DigestFilter>,
     password: String,
 }
 
 #[allow(unused)]
-impl<'a> Hacker<'a> {
+impl Hacker {
     const LEADING_ZEROS: usize = 5;
-    pub(crate) fn new(door_id: &'a str) -> Self {
+    pub(crate) fn new(door_id: &str) -> Self {
         Self {
-            door_id,
-            nonce: 0,
+            miner: Miner::new(door_id, Self::is_interesting),
             password: String::new(),
         }
     }
+    fn is_interesting(digest: &Digest) -> bool {
+        leading_zero_nibbles(digest) >= Self::LEADING_ZEROS
+    }
     pub(crate) fn get_next(&mut self) {
-        let leader = &"0".repeat(Self::LEADING_ZEROS);
-        loop {
-            let digest = md5::compute(format!("{}{}", self.door_id, self.nonce));
-            self.nonce += 1;
-            let hash = format!("{:x}", digest);
-            if hash.starts_with(leader) {
-                self.password
-                    .push(hash.chars().nth(Self::LEADING_ZEROS).unwrap());
-                break;
-            }
-
-            if self.nonce % 1_000_000 == 0 {
-                println!("nonce: {}", self.nonce);
-            }
-        }
+        let hit = self.miner.next().unwrap();
+        self.password
+            .push(hex_char(nibble(&hit.digest, Self::LEADING_ZEROS)));
     }
     pub(crate) fn get_password(&self) -> &str {
         &self.password
     }
 }
 
-struct HackerV2<'a> {
-    door_id: &'a str,
-    nonce: usize,
+struct HackerV2 {
+    door_id: String,
+    miner: Miner<DigestFilter>,
     password: [Option<char>; 8],
+    checkpoint: Option<CheckpointFile>,
 }
 
-impl<'a> HackerV2<'a> {
+impl HackerV2 {
     const LEADING_ZEROS: usize = 5;
-    pub(crate) fn new(door_id: &'a str) -> Self {
+    const UNKNOWN: char = '_';
+    pub(crate) fn new(door_id: &str) -> Self {
         Self {
-            door_id,
-            nonce: 0,
+            door_id: door_id.to_string(),
+            miner: Miner::new(door_id, Self::is_interesting),
             password: [None; 8],
+            checkpoint: None,
         }
     }
+    pub(crate) fn with_checkpoint(mut self, file: CheckpointFile) -> Self {
+        if let Some(checkpoint) = file.load() {
+            if let Err(e) = self.resume(&checkpoint) {
+                warn!("not resuming from checkpoint: {}", e);
+            }
+        }
+        self.checkpoint = Some(file);
+        self
+    }
+    fn resume(&mut self, checkpoint: &Checkpoint) -> Result<()> {
+        let door_id = checkpoint.get("door_id")?;
+        if door_id != self.door_id {
+            return Err(anyhow!("checkpoint is for door id '{}'", door_id));
+        }
+        let password: Vec<char> = checkpoint.get("password")?.chars().collect();
+        if password.len() != self.password.len() {
+            return Err(anyhow!("checkpoint password has the wrong length"));
+        }
+        let nonce = checkpoint.parse("nonce")?;
+        for (slot, c) in self.password.iter_mut().zip(password) {
+            *slot = Some(c).filter(|&c| c != Self::UNKNOWN);
+        }
+        self.miner.seek(nonce);
+        println!(
+            "resuming from nonce {} with {}/{} characters found",
+            nonce,
+            self.resolved_count(),
+            self.password.len()
+        );
+        Ok(())
+    }
+    fn to_checkpoint(&self) -> Checkpoint {
+        let mut checkpoint = Checkpoint::default();
+        checkpoint
+            .push("door_id", &self.door_id)
+            .push("nonce", self.miner.resume_nonce())
+            .push(
+                "password",
+                self.password
+                    .iter()
+                    .map(|c| c.unwrap_or(Self::UNKNOWN))
+                    .collect::<String>(),
+            );
+        checkpoint
+    }
+    fn save_checkpoint(&mut self, force: bool) {
+        if let Some(mut file) = self.checkpoint.take() {
+            let result = if force {
+                file.save(&self.to_checkpoint())
+            } else {
+                file.save_if_due(|| self.to_checkpoint())
+            };
+            if let Err(e) = result {
+                warn!("could not save checkpoint: {}", e);
+            }
+            self.checkpoint = Some(file);
+        }
+    }
+    fn is_interesting(digest: &Digest) -> bool {
+        leading_zero_nibbles(digest) >= Self::LEADING_ZEROS
+            && (nibble(digest, Self::LEADING_ZEROS) as usize) < 8
+    }
+    pub(crate) fn resolved_count(&self) -> usize {
+        self.password.iter().filter(|p| p.is_some()).count()
+    }
+    pub(crate) fn is_resolved(&self) -> bool {
+        self.resolved_count() == self.password.len()
+    }
     pub(crate) fn get_next(&mut self) {
-        if self.password.iter().all(|p| p.is_some()) {
+        if self.is_resolved() {
             panic!("already resolved password");
         }
-        let leader = &"0".repeat(Self::LEADING_ZEROS);
         loop {
-            let digest = md5::compute(format!("{}{}", self.door_id, self.nonce));
-            self.nonce += 1;
-            let hash = format!("{:x}", digest);
-            if hash.starts_with(leader) {
-                let position = hash
-                    .chars()
-                    .nth(Self::LEADING_ZEROS)
-                    .unwrap()
-                    .to_digit(16)
-                    .unwrap() as usize;
-                if position < 8 && self.password[position].is_none() {
-                    let c = hash.chars().nth(Self::LEADING_ZEROS + 1).unwrap();
-                    self.password[position] = Some(c);
-                    break;
+            let hit = match self.miner.try_next() {
+                Some(hit) => hit,
+                None => {
+                    self.save_checkpoint(false);
+                    continue;
                 }
-            }
-
-            if self.nonce % 1_000_000 == 0 {
-                println!("nonce: {}", self.nonce);
+            };
+            let position = nibble(&hit.digest, Self::LEADING_ZEROS) as usize;
+            if self.password[position].is_none() {
+                let c = hex_char(nibble(&hit.digest, Self::LEADING_ZEROS + 1));
+                self.password[position] = Some(c);
+                self.save_checkpoint(true);
+                break;
             }
         }
     }
@@ -101,6 +216,14 @@ impl<'a> HackerV2<'a> {
             .collect::<Result<String, _>>()
             .unwrap()
     }
+    pub(crate) fn finish(self) -> String {
+        if let Some(file) = self.checkpoint.as_ref() {
+            if let Err(e) = file.remove() {
+                warn!("could not remove checkpoint: {}", e);
+            }
+        }
+        self.get_password()
+    }
 }
 
 fn _get_input() -> &'static str {
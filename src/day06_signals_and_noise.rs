@@ -1,38 +1,240 @@
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 
 pub(crate) fn run() {
     let _input = "eedadn\ndrvtee\neandsr\nraavrd\natevrs\ntsrnev\nsdttsa\nrasrtv\nnssdts\nntnada\nsvetve\ntesnvt\nvntsnd\nvrdear\ndvrsen\nenarar";
     let _input = _get_input();
 
-    let message = decode(_input, true);
-    println!("the message is {}", message);
+    let table = FrequencyTable::new(_input);
+    let ties = TiePolicy::Alphabetical;
+
+    let most_common = Ranking::most_common(0, ties);
+    println!("the message is {}", table.decode(&most_common));
+    let least_common = Ranking::least_common(0, ties);
+    println!("the message is {}", table.decode(&least_common));
+    println!("{}", table.report(&least_common));
+
+    for other in TiePolicy::ALL.into_iter().filter(|&other| other != ties) {
+        let ranking = Ranking::least_common(0, other);
+        let message = table.decode(&ranking);
+        if message != table.decode(&least_common) {
+            println!("ties by {:?} would give {}", other, message);
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Order {
+    MostCommon,
+    LeastCommon,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum TiePolicy {
+    Alphabetical,
+    FirstSeen,
+    LastSeen,
+}
+
+impl TiePolicy {
+    const ALL: [TiePolicy; 3] = [Self::Alphabetical, Self::FirstSeen, Self::LastSeen];
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Ranking {
+    order: Order,
+    k: usize,
+    ties: TiePolicy,
+}
+
+impl Ranking {
+    fn most_common(k: usize, ties: TiePolicy) -> Self {
+        Self {
+            order: Order::MostCommon,
+            k,
+            ties,
+        }
+    }
+    fn least_common(k: usize, ties: TiePolicy) -> Self {
+        Self {
+            order: Order::LeastCommon,
+            k,
+            ties,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct CharStats {
+    c: char,
+    count: usize,
+    first_seen: usize,
+    last_seen: usize,
+}
+
+#[derive(Debug, Default)]
+struct Column {
+    stats: HashMap<char, CharStats>,
+    total: usize,
 }
 
-fn decode(input: &str, get_least_common: bool) -> String {
-    let lines: Vec<&str> = input.split('\n').collect();
-    let len = lines.iter().next().unwrap().len();
-    assert!(lines.iter().all(|line| line.len() == len));
+impl Column {
+    fn add(&mut self, c: char, line: usize) {
+        let stats = self.stats.entry(c).or_insert(CharStats {
+            c,
+            count: 0,
+            first_seen: line,
+            last_seen: line,
+        });
+        stats.count += 1;
+        stats.last_seen = line;
+        self.total += 1;
+    }
+    fn ranked(&self, ranking: &Ranking) -> Vec<CharStats> {
+        let mut ranked: Vec<CharStats> = self.stats.values().copied().collect();
+        ranked.sort_by(|a, b| {
+            let by_count = match ranking.order {
+                Order::MostCommon => b.count.cmp(&a.count),
+                Order::LeastCommon => a.count.cmp(&b.count),
+            };
+            by_count.then_with(|| match ranking.ties {
+                TiePolicy::Alphabetical => a.c.cmp(&b.c),
+                TiePolicy::FirstSeen => a.first_seen.cmp(&b.first_seen),
+                TiePolicy::LastSeen => b.last_seen.cmp(&a.last_seen),
+            })
+        });
+        ranked
+    }
+    fn entropy(&self) -> f64 {
+        self.stats
+            .values()
+            .map(|stats| stats.count as f64 / self.total as f64)
+            .map(|p| -p * p.log2())
+            .sum()
+    }
+}
+
+#[derive(Debug)]
+struct ColumnSummary {
+    chosen: Option<CharStats>,
+    tied: bool,
+    total: usize,
+    entropy: f64,
+}
+
+impl ColumnSummary {
+    fn confidence(&self) -> f64 {
+        self.chosen
+            .map(|stats| stats.count as f64 / self.total as f64)
+            .unwrap_or(0.0)
+    }
+}
+
+struct FrequencyTable {
+    columns: Vec<Column>,
+    lines: usize,
+}
+
+impl FrequencyTable {
+    const MISSING: char = '_';
 
-    let mut result = String::new();
-    for i in 0..len {
-        let mut counts: Vec<(char, usize)> = lines
+    fn new(input: &str) -> Self {
+        let mut columns: Vec<Column> = vec![];
+        let mut lines = 0;
+        for (line_index, line) in input.lines().enumerate() {
+            for (i, c) in line.chars().enumerate() {
+                if i == columns.len() {
+                    columns.push(Column::default());
+                }
+                columns[i].add(c, line_index);
+            }
+            lines += 1;
+        }
+        Self { columns, lines }
+    }
+    fn summarize(&self, ranking: &Ranking) -> Vec<ColumnSummary> {
+        self.columns
             .iter()
-            .fold(HashMap::new(), |mut map, next| {
-                let c = next.chars().nth(i).unwrap();
-                *map.entry(c).or_default() += 1usize;
-                map
+            .map(|column| {
+                let ranked = column.ranked(ranking);
+                let chosen = ranked.get(ranking.k).copied();
+                let tied = chosen
+                    .map(|chosen| {
+                        ranked
+                            .iter()
+                            .filter(|other| other.count == chosen.count)
+                            .count()
+                            > 1
+                    })
+                    .unwrap_or(false);
+                ColumnSummary {
+                    chosen,
+                    tied,
+                    total: column.total,
+                    entropy: column.entropy(),
+                }
             })
+            .collect()
+    }
+    fn decode(&self, ranking: &Ranking) -> String {
+        self.summarize(ranking)
             .into_iter()
-            .collect();
-        if get_least_common {
-            counts.sort_by(|&a, &b| a.1.cmp(&b.1));
-        } else {
-            counts.sort_by(|&a, &b| b.1.cmp(&a.1));
+            .map(|summary| summary.chosen.map(|s| s.c).unwrap_or(Self::MISSING))
+            .collect()
+    }
+    fn report(&self, ranking: &Ranking) -> Report {
+        Report {
+            ranking: *ranking,
+            lines: self.lines,
+            columns: self.summarize(ranking),
         }
-        result.push(counts[0].0);
     }
+}
+
+struct Report {
+    ranking: Ranking,
+    lines: usize,
+    columns: Vec<ColumnSummary>,
+}
 
-    result
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let order = match self.ranking.order {
+            Order::MostCommon => "most",
+            Order::LeastCommon => "least",
+        };
+        writeln!(
+            f,
+            "rank {} {} common, ties by {:?}, {} lines",
+            self.ranking.k + 1,
+            order,
+            self.ranking.ties,
+            self.lines
+        )?;
+        writeln!(
+            f,
+            "{:>6} {:>4} {:>9} {:>10} {:>8}",
+            "column", "char", "count", "confidence", "entropy"
+        )?;
+        for (i, column) in self.columns.iter().enumerate() {
+            let (c, count) = column
+                .chosen
+                .map(|stats| (stats.c, stats.count))
+                .unwrap_or((FrequencyTable::MISSING, 0));
+            let count = format!("{}/{}", count, column.total);
+            writeln!(
+                f,
+                "{:>6} {:>4} {:>9} {:>9.1}% {:>8.3}{}",
+                i,
+                c,
+                count,
+                column.confidence() * 100.0,
+                column.entropy,
+                if column.tied { " (tied)" } else { "" }
+            )?;
+        }
+        Ok(())
+    }
 }
 
 fn _get_input() -> &'static str {
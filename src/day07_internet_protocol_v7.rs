@@ -1,5 +1,8 @@
-use std::fmt::{Debug, Formatter};
+use crate::palindrome::{is_complement, PalindromePattern};
+use anyhow::{anyhow, Result};
+use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
+use thiserror::Error;
 
 pub(crate) fn run() {
     let _input = "abba[mnop]qrst
@@ -14,26 +17,48 @@ zazbz[bzb]cdb";
 
     let addresses: Vec<IpV7> = _input
         .lines()
-        .map(|line| line.parse())
-        .collect::<Result<_, _>>()
-        .unwrap();
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse()
+                .unwrap_or_else(|e| panic!("invalid address on line {}: {}", i + 1, e))
+        })
+        .collect();
+    assert_eq!(
+        "ab[cd[ef]]gh".parse::<IpV7>().err(),
+        Some(ParseError::NestedBracket { position: 5 })
+    );
+    assert_eq!(
+        "ab[cd".parse::<IpV7>().err(),
+        Some(ParseError::Unclosed { position: 2 })
+    );
 
-    let support_tls = addresses
-        .iter()
-        .filter(|address| address.supports_tls())
-        .count();
-    println!("{} addresses support tls", support_tls);
-    println!();
+    let protocols = [
+        Protocol::new("tls", "abba outside, no abba inside").unwrap(),
+        Protocol::new("ssl", "aba outside with bab inside").unwrap(),
+    ];
 
-    let support_ssl = addresses
-        .iter()
-        .filter(|address| address.supports_ssl())
-        .count();
-    println!("{} addresses support ssl", support_ssl);
+    for protocol in protocols.iter() {
+        let mut supported = 0;
+        for address in addresses.iter() {
+            let verdict = protocol.explain(address);
+            if verdict.is_supported() {
+                println!(
+                    "{} supports {}: {}",
+                    address.highlight(&verdict.evidence()),
+                    protocol.name,
+                    verdict
+                );
+                supported += 1;
+            }
+        }
+        println!("{} addresses support {}", supported, protocol.name);
+        println!();
+    }
 }
 
 struct Sequence {
     value: Vec<char>,
+    start: usize,
     is_inside_square_brackets: bool,
 }
 
@@ -58,48 +83,242 @@ impl Debug for Sequence {
 }
 
 impl Sequence {
-    pub(crate) fn is_abba(&self) -> bool {
-        self.value
-            .windows(4)
-            .any(|c| c[0] != c[1] && c[0] == c[3] && c[1] == c[2])
+    fn find<'a>(&'a self, pattern: &'a PalindromePattern) -> impl Iterator<Item = Match> + 'a {
+        pattern.find(&self.value).map(|i| Match {
+            position: self.start + i,
+            text: self.value[i..i + pattern.len()].iter().collect(),
+            inside_brackets: self.is_inside_square_brackets,
+        })
     }
-    pub(crate) fn get_abas(&self) -> Vec<Aba> {
-        self.value
-            .windows(3)
-            .filter_map(|c| Aba::try_from(c))
-            .collect()
+}
+
+#[derive(Debug, Clone)]
+struct Match {
+    position: usize,
+    text: String,
+    inside_brackets: bool,
+}
+
+impl Match {
+    fn is_complement_of(&self, other: &Self) -> bool {
+        let a: Vec<char> = self.text.chars().collect();
+        let b: Vec<char> = other.text.chars().collect();
+        is_complement(&a, &b)
+    }
+}
+
+impl Display for Match {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' at {}", self.text, self.position)
     }
 }
 
-struct Aba {
-    a: char,
-    b: char,
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Region {
+    Outside,
+    Inside,
 }
 
-impl Aba {
-    pub(crate) fn is_complement_of(&self, other: &Self) -> bool {
-        self.a == other.b && self.b == other.a
+impl Region {
+    fn contains(&self, m: &Match) -> bool {
+        m.inside_brackets == (*self == Region::Inside)
     }
-    pub(crate) fn try_from(chars: &[char]) -> Option<Self> {
-        if chars.len() == 3 && chars[0] != chars[1] && chars[0] == chars[2] {
-            Some(Self {
-                a: chars[0],
-                b: chars[1],
-            })
-        } else {
-            None
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Region::Outside => write!(f, "outside"),
+            Region::Inside => write!(f, "inside"),
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "outside" => Self::Outside,
+            "inside" => Self::Inside,
+            _ => return Err(anyhow!("invalid region '{}'", s)),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Rule {
+    Present(PalindromePattern, Region),
+    Absent(PalindromePattern, Region),
+    Paired(PalindromePattern, Region, PalindromePattern, Region),
+}
+
+impl Rule {
+    fn evaluate(&self, address: &IpV7) -> RuleOutcome {
+        let (satisfied, evidence) = match self {
+            Rule::Present(pattern, region) => match address.find(pattern, *region).next() {
+                Some(m) => (true, vec![m]),
+                None => (false, vec![]),
+            },
+            Rule::Absent(pattern, region) => match address.find(pattern, *region).next() {
+                Some(m) => (false, vec![m]),
+                None => (true, vec![]),
+            },
+            Rule::Paired(first, first_region, second, second_region) => {
+                let seconds: Vec<Match> = address.find(second, *second_region).collect();
+                address
+                    .find(first, *first_region)
+                    .find_map(|a| {
+                        seconds
+                            .iter()
+                            .find(|b| a.is_complement_of(b))
+                            .map(|b| vec![a.clone(), b.clone()])
+                    })
+                    .map(|evidence| (true, evidence))
+                    .unwrap_or((false, vec![]))
+            }
+        };
+        RuleOutcome {
+            rule: self.clone(),
+            satisfied,
+            evidence,
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rule::Present(pattern, region) => write!(f, "{} {}", pattern, region),
+            Rule::Absent(pattern, region) => write!(f, "no {} {}", pattern, region),
+            Rule::Paired(first, first_region, second, second_region) => write!(
+                f,
+                "{} {} with {} {}",
+                first, first_region, second, second_region
+            ),
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        Ok(match words[..] {
+            [pattern, region] => Self::Present(pattern.parse()?, region.parse()?),
+            ["no", pattern, region] => Self::Absent(pattern.parse()?, region.parse()?),
+            [first, first_region, "with", second, second_region] => Self::Paired(
+                first.parse()?,
+                first_region.parse()?,
+                second.parse()?,
+                second_region.parse()?,
+            ),
+            _ => return Err(anyhow!("invalid rule '{}'", s)),
+        })
+    }
+}
+
+struct Protocol {
+    name: &'static str,
+    rules: Vec<Rule>,
+}
+
+impl Protocol {
+    fn new(name: &'static str, rules: &str) -> Result<Self> {
+        Ok(Self {
+            name,
+            rules: rules
+                .split(',')
+                .map(|rule| rule.trim().parse())
+                .collect::<Result<_>>()?,
+        })
+    }
+    fn explain(&self, address: &IpV7) -> Verdict {
+        Verdict {
+            outcomes: self
+                .rules
+                .iter()
+                .map(|rule| rule.evaluate(address))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RuleOutcome {
+    rule: Rule,
+    satisfied: bool,
+    evidence: Vec<Match>,
+}
+
+impl Display for RuleOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            self.rule,
+            if self.satisfied { "holds" } else { "fails" }
+        )?;
+        if !self.evidence.is_empty() {
+            let evidence: Vec<String> = self.evidence.iter().map(|m| m.to_string()).collect();
+            write!(f, " ({})", evidence.join(", "))?;
         }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Verdict {
+    outcomes: Vec<RuleOutcome>,
+}
+
+impl Verdict {
+    fn is_supported(&self) -> bool {
+        self.outcomes.iter().all(|outcome| outcome.satisfied)
+    }
+    fn evidence(&self) -> Vec<&Match> {
+        self.outcomes
+            .iter()
+            .flat_map(|outcome| outcome.evidence.iter())
+            .collect()
     }
 }
 
-struct IpV7(Vec<Sequence>);
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let outcomes: Vec<String> = self.outcomes.iter().map(|o| o.to_string()).collect();
+        write!(f, "{}", outcomes.join("; "))
+    }
+}
+
+#[derive(Error, Debug, Eq, PartialEq)]
+enum ParseError {
+    #[error("empty address")]
+    Empty,
+    #[error("invalid character '{c}' at {position}")]
+    InvalidChar { c: char, position: usize },
+    #[error("nested '[' at {position}")]
+    NestedBracket { position: usize },
+    #[error("unmatched ']' at {position}")]
+    UnmatchedClose { position: usize },
+    #[error("'[' at {position} is never closed")]
+    Unclosed { position: usize },
+    #[error("empty brackets at {position}")]
+    EmptyBrackets { position: usize },
+}
+
+struct IpV7 {
+    address: String,
+    sequences: Vec<Sequence>,
+}
 
 impl Debug for IpV7 {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "{}",
-            self.0
+            self.sequences
                 .iter()
                 .map(|seq| format!("{:?}", seq))
                 .collect::<String>()
@@ -108,65 +327,84 @@ impl Debug for IpV7 {
 }
 
 impl IpV7 {
-    pub(crate) fn supports_tls(&self) -> bool {
-        let mut any = false;
-        for sequence in self.0.iter() {
-            if sequence.is_abba() {
-                if sequence.is_inside_square_brackets {
-                    return false;
+    fn find<'a>(
+        &'a self,
+        pattern: &'a PalindromePattern,
+        region: Region,
+    ) -> impl Iterator<Item = Match> + 'a {
+        self.sequences
+            .iter()
+            .flat_map(move |seq| seq.find(pattern))
+            .filter(move |m| region.contains(m))
+    }
+    fn highlight(&self, evidence: &[&Match]) -> String {
+        self.address
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                if evidence
+                    .iter()
+                    .any(|m| (m.position..m.position + m.text.len()).contains(&i))
+                {
+                    c.to_ascii_uppercase()
                 } else {
-                    any = true;
+                    c
                 }
-            }
-        }
-
-        if any {
-            println!("{:?} supports tls", self);
-        }
-        any
-    }
-    pub(crate) fn supports_ssl(&self) -> bool {
-        let super_net_abas: Vec<_> = self
-            .0
-            .iter()
-            .filter(|seq| !seq.is_inside_square_brackets)
-            .flat_map(|seq| seq.get_abas())
-            .collect();
-        let hyper_net_abas: Vec<_> = self
-            .0
-            .iter()
-            .filter(|seq| seq.is_inside_square_brackets)
-            .flat_map(|seq| seq.get_abas())
-            .collect();
-        let result = super_net_abas.into_iter().any(|super_aba| {
-            hyper_net_abas
-                .iter()
-                .any(|hyper_aba| super_aba.is_complement_of(hyper_aba))
-        });
-
-        if result {
-            println!("{:?} supports ssl", self);
-        }
-        result
+            })
+            .collect()
     }
 }
 
 impl FromStr for IpV7 {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseError::Empty);
+        }
         let mut sequences: Vec<Sequence> = Default::default();
-
-        let mut i = if s.starts_with('[') { 1usize } else { 0usize };
-        for s in s.split(&['[', ']']) {
-            i += 1;
-            sequences.push(Sequence {
-                value: s.chars().collect(),
-                is_inside_square_brackets: i % 2 == 0,
-            })
+        let mut open: Option<usize> = None;
+        let mut start = 0;
+        let mut push = |start: usize, end: usize, inside: bool| {
+            if end > start {
+                sequences.push(Sequence {
+                    value: s[start..end].chars().collect(),
+                    start,
+                    is_inside_square_brackets: inside,
+                })
+            }
+        };
+        for (position, c) in s.char_indices() {
+            match c {
+                '[' => {
+                    if open.is_some() {
+                        return Err(ParseError::NestedBracket { position });
+                    }
+                    push(start, position, false);
+                    open = Some(position);
+                    start = position + 1;
+                }
+                ']' => {
+                    let opened = open.take().ok_or(ParseError::UnmatchedClose { position })?;
+                    if position == opened + 1 {
+                        return Err(ParseError::EmptyBrackets { position: opened });
+                    }
+                    push(start, position, true);
+                    start = position + 1;
+                }
+                c if c.is_ascii_lowercase() => {}
+                c => return Err(ParseError::InvalidChar { c, position }),
+            }
+        }
+        if let Some(position) = open {
+            return Err(ParseError::Unclosed { position });
         }
+        push(start, s.len(), false);
 
-        Ok(Self(sequences))
+        Ok(Self {
+            address: s.to_string(),
+            sequences,
+        })
     }
 }
 
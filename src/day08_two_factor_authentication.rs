@@ -1,39 +1,113 @@
+use anyhow::{anyhow, Result};
+use log::warn;
+use std::borrow::Cow;
 use std::fmt::{Debug, Formatter};
-use std::str::{FromStr, Split};
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
 
 pub(crate) fn run() {
-    let _input = "rect 3x2\nrotate column x=1 by 1\nrotate row y=0 by 4\nrotate column x=1 by 1";
+    let _input =
+        "screen 7x3\nrect 3x2\nrotate column x=1 by 1\nrotate row y=0 by 4\nrotate column x=1 by 1";
     let _input = _get_input();
 
-    let instructions: Vec<Instruction> = _input
+    let inverse_replay = true;
+    let default_size = (50, 6);
+
+    let (size, program) = match _input.split_once('\n') {
+        Some((header, program)) if header.starts_with("screen ") => (
+            Some(parse_size(&header["screen ".len()..]).unwrap()),
+            program,
+        ),
+        _ => (None, _input),
+    };
+    let cli_size = std::env::args()
+        .nth(2)
+        .map(|arg| parse_size(&arg).expect("screen size should look like 50x6"));
+    let (width, height) = cli_size.or(size).unwrap_or(default_size);
+
+    let instructions: Vec<Instruction> = program
         .lines()
         .map(|line| line.parse())
         .collect::<Result<_, _>>()
         .unwrap();
 
-    let mut _lcd = Lcd::new(7, 3);
-    let mut _lcd = Lcd::new(50, 6);
-    let mut lcd = _lcd;
+    let mut lcd = Lcd::new(width, height).unwrap();
+    for (i, instruction) in instructions.iter().enumerate() {
+        if let Err(e) = lcd.check(instruction) {
+            panic!("instruction {} does not fit the screen: {}", i + 1, e);
+        }
+    }
+    let mut undos = vec![];
+
+    let frame_export = FrameExport::Print;
+    // let frame_export = FrameExport::Ansi { delay: Duration::from_millis(30) };
+    // let frame_export = FrameExport::Png { dir: "day08_frames" };
+    // let frame_export = FrameExport::Gif { path: "day08.gif", delay: Duration::from_millis(50) };
 
+    let mut frames = FrameWriter::new(&frame_export, &lcd).unwrap();
     for instruction in instructions.iter() {
-        lcd.apply(instruction);
-        println!("{:?}", lcd);
+        undos.push(lcd.apply(instruction).unwrap());
+        frames.write(&lcd).unwrap();
     }
+    frames.finish(&lcd).unwrap();
+    let final_lcd = lcd.clone();
 
     println!("LCD:\n{:?}", lcd);
     println!("lit count: {}", lcd.lit_count());
+    match lcd.read_text() {
+        Ok(text) => println!("text: {}", text),
+        Err(e) => println!("could not read text: {}", e),
+    }
+
+    if inverse_replay {
+        let exact = undos
+            .iter()
+            .filter(|undo| matches!(undo, Undo::Apply(_)))
+            .count();
+        for undo in undos.iter().rev() {
+            lcd.undo(undo).unwrap();
+        }
+        assert_eq!(
+            lcd,
+            Lcd::new(width, height).unwrap(),
+            "replay did not return to start"
+        );
+        println!(
+            "inverse replay restored the blank screen: {} of {} steps inverted exactly",
+            exact,
+            undos.len()
+        );
+        match final_lcd.unapply_all(&instructions) {
+            Some(start) => println!("without a journal the start has {} lit", start.lit_count()),
+            None => println!("without a journal the start cannot be recovered"),
+        }
+    }
 }
 
-struct Lcd(Vec<Vec<Pixel>>);
+fn parse_size(s: &str) -> Result<(usize, usize), ()> {
+    let (width, height) = s.trim().split_once('x').ok_or(())?;
+    Ok((width.parse().or(Err(()))?, height.parse().or(Err(()))?))
+}
+
+#[derive(Clone, Eq, PartialEq)]
+struct Lcd {
+    width: usize,
+    height: usize,
+    rows: Vec<u128>,
+}
 
 impl Debug for Lcd {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for row in self.0.iter() {
-            write!(
+        for y in 0..self.height {
+            writeln!(
                 f,
-                "{}\n",
-                row.iter()
-                    .map(|p| if p.0 { '#' } else { '.' })
+                "{}",
+                (0..self.width)
+                    .map(|x| if self.get(x, y) { '#' } else { '.' })
                     .collect::<String>()
             )?;
         }
@@ -42,83 +116,369 @@ impl Debug for Lcd {
 }
 
 impl Lcd {
-    pub(crate) fn new(width: usize, height: usize) -> Self {
-        Self {
-            0: vec![vec![Pixel(false); width]; height],
+    const MAX_SIZE: usize = u128::BITS as usize;
+
+    pub(crate) fn new(width: usize, height: usize) -> Result<Self> {
+        if !(1..=Self::MAX_SIZE).contains(&width) || !(1..=Self::MAX_SIZE).contains(&height) {
+            return Err(anyhow!(
+                "screen size {}x{} is outside 1x1 to {}x{}",
+                width,
+                height,
+                Self::MAX_SIZE,
+                Self::MAX_SIZE
+            ));
         }
+        Ok(Self {
+            width,
+            height,
+            rows: vec![0; height],
+        })
     }
-    pub(crate) fn apply(&mut self, instruction: &Instruction) {
-        match instruction {
-            Instruction::Rect(width, height) => {
-                for row in &mut self.0[0..*height] {
-                    for pixel in &mut row[0..*width] {
-                        pixel.0 = true;
-                    }
-                }
+    pub(crate) fn get(&self, x: usize, y: usize) -> bool {
+        self.rows[y] & (1 << x) != 0
+    }
+    fn check(&self, instruction: &Instruction) -> Result<()> {
+        match *instruction {
+            Instruction::RotateRow(row, _) | Instruction::ShiftRow(row, _)
+                if row >= self.height =>
+            {
+                Err(anyhow!(
+                    "row {} is outside a screen {} pixels tall",
+                    row,
+                    self.height
+                ))
             }
+            Instruction::RotateColumn(col, _) | Instruction::ShiftColumn(col, _)
+                if col >= self.width =>
+            {
+                Err(anyhow!(
+                    "column {} is outside a screen {} pixels wide",
+                    col,
+                    self.width
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+    pub(crate) fn apply(&mut self, instruction: &Instruction) -> Result<Undo> {
+        self.check(instruction)?;
+        let undo = match self.inverse(instruction) {
+            Some(inverse) => Undo::Apply(inverse),
+            None => Undo::Restore(self.rows.clone()),
+        };
+        match *instruction {
+            Instruction::Rect(width, height) => self.fill(width, height, |row, mask| row | mask),
+            Instruction::Clear(width, height) => self.fill(width, height, |row, mask| row & !mask),
+            Instruction::Toggle(width, height) => self.fill(width, height, |row, mask| row ^ mask),
+            Instruction::Invert => self.fill(self.width, self.height, |row, mask| row ^ mask),
             Instruction::RotateRow(row, amount) => {
-                let reference = self.0[*row].clone();
-                let len = reference.len();
-                let amount = (((*amount as isize * -1) % len as isize) + len as isize) as usize;
-                for (i, val) in self.0[*row].iter_mut().enumerate() {
-                    *val = reference[(i + amount) % len];
-                }
+                self.rows[row] = rotate(self.rows[row], amount, self.width);
             }
             Instruction::RotateColumn(col, amount) => {
-                let reference: Vec<_> = self.0.iter().map(|row| &row[*col]).copied().collect();
-                let len = reference.len();
-                let amount = (((*amount as isize * -1) % len as isize) + len as isize) as usize;
-                for (i, row) in self.0.iter_mut().enumerate() {
-                    let val = &mut row[*col];
-                    *val = reference[(i + amount) % len];
-                }
+                let column = self.column(col);
+                self.set_column(col, rotate(column, amount, self.height));
+            }
+            Instruction::ShiftRow(row, amount) => {
+                self.rows[row] = shift(self.rows[row], amount, self.width);
+            }
+            Instruction::ShiftColumn(col, amount) => {
+                let column = self.column(col);
+                self.set_column(col, shift(column, amount, self.height));
             }
         }
+        Ok(undo)
     }
-    pub(crate) fn lit_count(&self) -> usize {
-        self.0
+    pub(crate) fn undo(&mut self, undo: &Undo) -> Result<()> {
+        match undo {
+            Undo::Apply(inverse) => {
+                self.apply(inverse)?;
+            }
+            Undo::Restore(rows) => self.rows.clone_from(rows),
+        }
+        Ok(())
+    }
+    fn inverse(&self, instruction: &Instruction) -> Option<Instruction> {
+        Some(match *instruction {
+            Instruction::RotateRow(row, amount) => {
+                Instruction::RotateRow(row, self.width - amount % self.width)
+            }
+            Instruction::RotateColumn(col, amount) => {
+                Instruction::RotateColumn(col, self.height - amount % self.height)
+            }
+            Instruction::Toggle(width, height) => Instruction::Toggle(width, height),
+            Instruction::Invert => Instruction::Invert,
+            Instruction::Rect(..)
+            | Instruction::Clear(..)
+            | Instruction::ShiftRow(..)
+            | Instruction::ShiftColumn(..) => return None,
+        })
+    }
+    pub(crate) fn unapply_all(&self, instructions: &[Instruction]) -> Option<Self> {
+        let mut lcd = self.clone();
+        for instruction in instructions.iter().rev() {
+            let inverse = lcd.inverse(instruction)?;
+            lcd.apply(&inverse).ok()?;
+        }
+        Some(lcd)
+    }
+    fn fill(&mut self, width: usize, height: usize, op: impl Fn(u128, u128) -> u128) {
+        let mask = mask(width.min(self.width));
+        for row in self.rows.iter_mut().take(height) {
+            *row = op(*row, mask);
+        }
+    }
+    fn column(&self, col: usize) -> u128 {
+        self.rows
             .iter()
-            .map(|row| row.iter().filter(|p| p.0).count())
-            .sum()
+            .enumerate()
+            .fold(0, |column, (y, row)| column | ((row >> col) & 1) << y)
+    }
+    fn set_column(&mut self, col: usize, column: u128) {
+        for (y, row) in self.rows.iter_mut().enumerate() {
+            *row = (*row & !(1 << col)) | ((column >> y) & 1) << col;
+        }
+    }
+    pub(crate) fn lit_count(&self) -> usize {
+        self.rows.iter().map(|row| row.count_ones() as usize).sum()
+    }
+    pub(crate) fn read_text(&self) -> Result<String> {
+        if self.height != GLYPH_HEIGHT {
+            return Err(anyhow!(
+                "can only read text from a screen {} pixels tall",
+                GLYPH_HEIGHT
+            ));
+        }
+        Ok((0..self.width / GLYPH_SPACING)
+            .map(|i| {
+                let x0 = i * GLYPH_SPACING;
+                let key = self.rows.iter().fold(0u32, |key, row| {
+                    key << GLYPH_WIDTH | ((row >> x0) & mask(GLYPH_WIDTH)) as u32
+                });
+                GLYPHS
+                    .iter()
+                    .find(|(_, glyph)| glyph_key(glyph) == key)
+                    .map(|&(c, _)| c)
+                    .unwrap_or('?')
+            })
+            .collect())
     }
 }
 
-#[derive(Clone, Copy)]
-struct Pixel(bool);
+fn mask(len: usize) -> u128 {
+    if len >= Lcd::MAX_SIZE {
+        u128::MAX
+    } else {
+        (1 << len) - 1
+    }
+}
+
+fn shift(bits: u128, amount: usize, len: usize) -> u128 {
+    bits.checked_shl(amount as u32).unwrap_or(0) & mask(len)
+}
+
+fn rotate(bits: u128, amount: usize, len: usize) -> u128 {
+    let amount = amount % len;
+    if amount == 0 {
+        return bits;
+    }
+    ((bits << amount) | (bits >> (len - amount))) & mask(len)
+}
+
+#[allow(unused)]
+enum FrameExport {
+    Print,
+    Ansi { delay: Duration },
+    Png { dir: &'static str },
+    Gif { path: &'static str, delay: Duration },
+}
+
+enum FrameWriter {
+    Print,
+    Ansi {
+        delay: Duration,
+        frame: usize,
+    },
+    Png {
+        dir: PathBuf,
+        frame: usize,
+    },
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        delay: Duration,
+    },
+}
+
+impl FrameWriter {
+    const SCALE: usize = 8;
+    const PALETTE: [u8; 6] = [0x0f, 0x0f, 0x23, 0xff, 0xff, 0x66];
+
+    fn new(export: &FrameExport, lcd: &Lcd) -> Result<Self> {
+        let mut writer = match *export {
+            FrameExport::Print => Self::Print,
+            FrameExport::Ansi { delay } => {
+                if std::io::stdout().is_terminal() {
+                    print!("\x1b[?25l\x1b[2J");
+                    Self::Ansi { delay, frame: 0 }
+                } else {
+                    warn!("stdout is not a terminal, printing frames instead");
+                    Self::Print
+                }
+            }
+            FrameExport::Png { dir } => {
+                std::fs::create_dir_all(dir)?;
+                Self::Png {
+                    dir: PathBuf::from(dir),
+                    frame: 0,
+                }
+            }
+            FrameExport::Gif { path, delay } => {
+                let (width, height) = Self::image_size(lcd);
+                let file = BufWriter::new(File::create(path)?);
+                let mut encoder =
+                    gif::Encoder::new(file, width as u16, height as u16, &Self::PALETTE)?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+                Self::Gif { encoder, delay }
+            }
+        };
+        if !matches!(writer, Self::Print) {
+            writer.write(lcd)?;
+        }
+        Ok(writer)
+    }
+    fn write(&mut self, lcd: &Lcd) -> Result<()> {
+        match self {
+            FrameWriter::Print => println!("{:?}", lcd),
+            FrameWriter::Ansi { delay, frame } => {
+                print!("\x1b[H{:?}\nframe {}", lcd, frame);
+                std::io::stdout().flush()?;
+                *frame += 1;
+                thread::sleep(*delay);
+            }
+            FrameWriter::Png { dir, frame } => {
+                Self::write_png(&dir.join(format!("frame_{:04}.png", frame)), lcd)?;
+                *frame += 1;
+            }
+            FrameWriter::Gif { encoder, delay } => {
+                let (width, height) = Self::image_size(lcd);
+                let pixels = Self::image_pixels(lcd);
+                let frame = gif::Frame {
+                    width: width as u16,
+                    height: height as u16,
+                    buffer: Cow::Borrowed(&pixels),
+                    delay: (delay.as_millis() / 10) as u16,
+                    ..gif::Frame::default()
+                };
+                encoder.write_frame(&frame)?;
+            }
+        }
+        Ok(())
+    }
+    fn finish(self, lcd: &Lcd) -> Result<()> {
+        match self {
+            FrameWriter::Print | FrameWriter::Gif { .. } => {}
+            FrameWriter::Ansi { .. } => println!("\x1b[?25h"),
+            FrameWriter::Png { dir, .. } => Self::write_png(&dir.join("final.png"), lcd)?,
+        }
+        Ok(())
+    }
+    fn image_size(lcd: &Lcd) -> (usize, usize) {
+        (lcd.width * Self::SCALE, lcd.height * Self::SCALE)
+    }
+    fn image_pixels(lcd: &Lcd) -> Vec<u8> {
+        let (width, height) = Self::image_size(lcd);
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| lcd.get(x / Self::SCALE, y / Self::SCALE) as u8))
+            .collect()
+    }
+    fn write_png(path: &Path, lcd: &Lcd) -> Result<()> {
+        let (width, height) = Self::image_size(lcd);
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(&Self::PALETTE[..]);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&Self::image_pixels(lcd))?;
+        Ok(())
+    }
+}
+
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+const GLYPH_SPACING: usize = 5;
+
+const GLYPHS: [(char, &str); 18] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Y', "#...#....#.#..#...#...#."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+fn glyph_key(glyph: &str) -> u32 {
+    glyph.as_bytes().chunks(GLYPH_WIDTH).fold(0, |key, row| {
+        let row = row
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (x, &c)| bits | ((c == b'#') as u32) << x);
+        key << GLYPH_WIDTH | row
+    })
+}
+
+enum Undo {
+    Apply(Instruction),
+    Restore(Vec<u128>),
+}
 
 enum Instruction {
     Rect(usize, usize),
+    Clear(usize, usize),
+    Toggle(usize, usize),
+    Invert,
     RotateRow(usize, usize),
     RotateColumn(usize, usize),
+    ShiftRow(usize, usize),
+    ShiftColumn(usize, usize),
 }
 
 impl FromStr for Instruction {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parse_next =
-            |parts: &mut Split<char>| parts.next().ok_or(())?.parse::<usize>().or(Err(()));
-        let mut words = s.split_whitespace();
-        Ok(match words.next().ok_or(())? {
-            "rect" => {
-                let mut parts = words.next().ok_or(())?.split('x');
-                Self::Rect(parse_next(&mut parts)?, parse_next(&mut parts)?)
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let index = |word: &str| word.get(2..).ok_or(())?.parse::<usize>().or(Err(()));
+        let amount = |word: &str| word.parse::<usize>().or(Err(()));
+        Ok(match words[..] {
+            ["rect", size] => {
+                let (width, height) = parse_size(size)?;
+                Self::Rect(width, height)
             }
-            "rotate" => match words.next().ok_or(())? {
-                "column" => {
-                    let col = words.next().ok_or(())?[2..].parse::<usize>().or(Err(()))?;
-                    words.next();
-                    let val = words.next().ok_or(())?.parse::<usize>().or(Err(()))?;
-                    Self::RotateColumn(col, val)
-                }
-                "row" => {
-                    let row = words.next().ok_or(())?[2..].parse::<usize>().or(Err(()))?;
-                    words.next();
-                    let val = words.next().ok_or(())?.parse::<usize>().or(Err(()))?;
-                    Self::RotateRow(row, val)
-                }
-                _ => return Err(()),
-            },
+            ["clear", size] => {
+                let (width, height) = parse_size(size)?;
+                Self::Clear(width, height)
+            }
+            ["toggle", size] => {
+                let (width, height) = parse_size(size)?;
+                Self::Toggle(width, height)
+            }
+            ["invert"] => Self::Invert,
+            ["rotate", "row", row, "by", val] => Self::RotateRow(index(row)?, amount(val)?),
+            ["rotate", "column", col, "by", val] => Self::RotateColumn(index(col)?, amount(val)?),
+            ["shift", "row", row, "by", val] => Self::ShiftRow(index(row)?, amount(val)?),
+            ["shift", "column", col, "by", val] => Self::ShiftColumn(index(col)?, amount(val)?),
             _ => return Err(()),
         })
     }
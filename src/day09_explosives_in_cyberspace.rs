@@ -1,129 +1,604 @@
-use std::str::{Chars, FromStr};
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::io::{self, BufRead, Read};
+use thiserror::Error;
 
 pub(crate) fn run() {
     let _input = "A(1x5)BC";
     let _input = "X(8x2)(3x3)ABCY";
     let _input = _get_input();
 
-    let message = decompress(_input);
-    // println!("message: {}", message);
-    println!("message length: {}", message.len());
+    let mode = Mode::Strict;
 
-    let message: Message = _input.parse().unwrap();
+    assert_eq!(
+        decoded_len::<u64>(b"A(3x3)BC", Version::V1, Mode::Strict),
+        Err(DecodeError::TruncatedSpan {
+            offset: 6,
+            width: 3,
+            available: 2
+        })
+    );
+    assert_eq!(
+        decoded_len::<u64>(b"A(1x", Version::V1, Mode::Strict),
+        Err(DecodeError::UnterminatedMarker { offset: 1 })
+    );
+
+    let mut decoder = Decoder::new(_input.as_bytes(), Version::V1).with_mode(mode);
+    let streamed = io::copy(&mut decoder, &mut io::sink()).unwrap();
+    assert_eq!(
+        decoded_len::<u64>(_input.as_bytes(), Version::V1, mode),
+        Ok(streamed)
+    );
+    // println!("message: {}", io::read_to_string(Decoder::new(_input.as_bytes(), Version::V1)).unwrap());
+    println!("message length: {}", streamed);
+
+    println!();
+    match decoded_len::<u64>(_input.as_bytes(), Version::V2, mode) {
+        Ok(len) => println!("recursive message length: {}", len),
+        Err(DecodeError::Overflow) => println!(
+            "recursive message length: {}",
+            decoded_len::<u128>(_input.as_bytes(), Version::V2, mode).unwrap()
+        ),
+        Err(e) => panic!("invalid input: {}", e),
+    }
 
     println!();
-    println!("recursive message length: {}", message.get_total_length());
+    let samples = [
+        (
+            Version::V1,
+            io::read_to_string(Decoder::new(_input.as_bytes(), Version::V1)).unwrap(),
+        ),
+        (
+            Version::V2,
+            "XABCABCABCABCABCABCY".repeat(3) + &"Q".repeat(100),
+        ),
+    ];
+    for (version, text) in samples.iter() {
+        for strategy in [Strategy::Greedy, Strategy::Shortest] {
+            let encoded = Encoder::new(*version, strategy)
+                .encode(text.as_bytes())
+                .unwrap();
+            println!(
+                "{:?} {:?} encoding: {} -> {} bytes, ratio {:.3}",
+                version,
+                strategy,
+                text.len(),
+                encoded.len(),
+                encoded.len() as f64 / text.len() as f64
+            );
+        }
+    }
 }
 
-struct Message {
-    repetition: usize,
-    length: usize,
-    children: Vec<Message>,
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Version {
+    V1,
+    V2,
 }
 
-impl Message {
-    fn set_repetition(&mut self, value: usize) {
-        self.repetition = value;
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Mode {
+    Lenient,
+    Strict,
+}
+
+#[derive(Error, Debug, Eq, PartialEq)]
+enum DecodeError {
+    #[error("malformed marker '({text}' at byte {offset}")]
+    MalformedMarker { offset: usize, text: String },
+    #[error("marker at byte {offset} is never closed")]
+    UnterminatedMarker { offset: usize },
+    #[error("span of {width} bytes at byte {offset} has only {available} bytes")]
+    TruncatedSpan {
+        offset: usize,
+        width: usize,
+        available: usize,
+    },
+    #[error("decoded length overflows")]
+    Overflow,
+}
+
+impl From<DecodeError> for io::Error {
+    fn from(e: DecodeError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Marker {
+    width: usize,
+    repeat: usize,
+}
+
+enum MarkerScan {
+    Marker(Marker),
+    Text(Vec<u8>),
+}
+
+enum Terminator {
+    Close,
+    Other(u8),
+    End,
+}
+
+fn read_marker<E>(
+    mut next: impl FnMut() -> Result<Option<u8>, E>,
+) -> Result<(Vec<u8>, Terminator), E> {
+    let mut text = vec![];
+    loop {
+        match next()? {
+            Some(b) if b.is_ascii_digit() || b == b'x' => text.push(b),
+            Some(b')') => return Ok((text, Terminator::Close)),
+            Some(b) => return Ok((text, Terminator::Other(b))),
+            None => return Ok((text, Terminator::End)),
+        }
+    }
+}
+
+fn scan_marker(
+    mode: Mode,
+    offset: usize,
+    mut text: Vec<u8>,
+    terminator: Terminator,
+) -> Result<MarkerScan, DecodeError> {
+    match mode {
+        Mode::Lenient => {
+            if let Terminator::Other(b) = terminator {
+                text.push(b);
+            }
+            Ok(match parse_marker(&text, mode) {
+                Some(marker) => MarkerScan::Marker(marker),
+                None => MarkerScan::Text(text),
+            })
+        }
+        Mode::Strict => {
+            let malformed = |mut text: Vec<u8>| {
+                if let Terminator::Other(b) = terminator {
+                    text.push(b);
+                }
+                DecodeError::MalformedMarker {
+                    offset,
+                    text: String::from_utf8_lossy(&text).into_owned(),
+                }
+            };
+            match terminator {
+                Terminator::End => Err(DecodeError::UnterminatedMarker { offset }),
+                Terminator::Other(_) => Err(malformed(text)),
+                Terminator::Close => match parse_marker(&text, mode) {
+                    Some(marker) => Ok(MarkerScan::Marker(marker)),
+                    None => Err(malformed(text)),
+                },
+            }
+        }
     }
-    pub(crate) fn get_total_length(&self) -> usize {
-        self.repetition
-            * (self.length
-                + self
-                    .children
-                    .iter()
-                    .map(|c| c.get_total_length())
-                    .sum::<usize>())
+}
+
+fn parse_marker(text: &[u8], mode: Mode) -> Option<Marker> {
+    let text = std::str::from_utf8(text).ok()?;
+    let mut parts = text.split('x');
+    let marker = Marker {
+        width: parts.next()?.parse().ok()?,
+        repeat: parts.next()?.parse().ok()?,
+    };
+    if mode == Mode::Strict && parts.next().is_some() {
+        return None;
     }
+    Some(marker)
+}
+
+struct Frame {
+    span: Vec<u8>,
+    origin: usize,
+    pos: usize,
+    repeats_left: usize,
 }
 
-impl FromStr for Message {
-    type Err = ();
+struct Decoder<R> {
+    input: io::Bytes<R>,
+    offset: usize,
+    version: Version,
+    mode: Mode,
+    frames: Vec<Frame>,
+    pending: VecDeque<u8>,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut result = String::new();
-        let mut chars = s.chars();
-        let mut children: Vec<Message> = Default::default();
-        while let Some(c) = chars.next() {
-            if c == '(' {
-                let marker_str = try_get_marker(&mut chars);
-                if let Some((width, repeat)) = parse_marker(&marker_str) {
-                    let mut buffer = String::with_capacity(width);
-                    for _ in 0..width {
-                        if let Some(c) = chars.next() {
-                            buffer.push(c);
+impl<R: BufRead> Decoder<R> {
+    pub(crate) fn new(input: R, version: Version) -> Self {
+        Self {
+            input: input.bytes(),
+            offset: 0,
+            version,
+            mode: Mode::Lenient,
+            frames: vec![],
+            pending: VecDeque::new(),
+        }
+    }
+    pub(crate) fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+    fn position(&self) -> usize {
+        match self.frames.last() {
+            Some(frame) => frame.origin + frame.pos,
+            None => self.offset,
+        }
+    }
+    fn next_raw(&mut self) -> io::Result<Option<u8>> {
+        match self.frames.last_mut() {
+            Some(frame) => {
+                let byte = frame.span.get(frame.pos).copied();
+                frame.pos += byte.is_some() as usize;
+                Ok(byte)
+            }
+            None => {
+                let byte = self.input.next().transpose()?;
+                self.offset += byte.is_some() as usize;
+                Ok(byte)
+            }
+        }
+    }
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            if let Some(byte) = self.pending.pop_front() {
+                return Ok(Some(byte));
+            }
+            if let Some(frame) = self.frames.last_mut() {
+                if frame.pos == frame.span.len() {
+                    frame.repeats_left -= 1;
+                    frame.pos = 0;
+                    if frame.repeats_left == 0 {
+                        self.frames.pop();
+                    }
+                    continue;
+                }
+            }
+            let offset = self.position();
+            let byte = match self.next_raw()? {
+                Some(byte) => byte,
+                None => return Ok(None),
+            };
+            if self.mode == Mode::Strict && self.frames.is_empty() && byte.is_ascii_whitespace() {
+                continue;
+            }
+            let expands = self.version == Version::V2 || self.frames.is_empty();
+            if byte != b'(' || !expands {
+                return Ok(Some(byte));
+            }
+            let (text, terminator) = read_marker(|| self.next_raw())?;
+            match scan_marker(self.mode, offset, text, terminator)? {
+                MarkerScan::Marker(marker) => {
+                    let origin = self.position();
+                    let mut span = Vec::with_capacity(marker.width.min(4096));
+                    while span.len() < marker.width {
+                        match self.next_raw()? {
+                            Some(byte) => span.push(byte),
+                            None => break,
+                        }
+                    }
+                    if self.mode == Mode::Strict && span.len() < marker.width {
+                        return Err(DecodeError::TruncatedSpan {
+                            offset: origin,
+                            width: marker.width,
+                            available: span.len(),
                         }
+                        .into());
+                    }
+                    if marker.repeat > 0 && !span.is_empty() {
+                        self.frames.push(Frame {
+                            span,
+                            origin,
+                            pos: 0,
+                            repeats_left: marker.repeat,
+                        });
                     }
-                    let mut child: Self = buffer.parse()?;
-                    child.set_repetition(repeat);
-                    children.push(child);
-                } else {
-                    result.push_str(&marker_str);
                 }
-            } else {
-                result.push(c);
+                MarkerScan::Text(text) => self.pending.extend(text),
             }
         }
-
-        Ok(Self {
-            repetition: 1,
-            length: result.len(),
-            children,
-        })
     }
 }
 
-fn try_get_marker(chars: &mut Chars) -> String {
-    let mut buffer = String::new();
-    while let Some(c) = chars.next() {
-        if !(c.is_numeric() || c == 'x') {
-            if c != ')' {
-                buffer.push(c);
+impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            match self.next_byte()? {
+                Some(byte) => {
+                    buf[written] = byte;
+                    written += 1;
+                }
+                None => break,
             }
-            break;
         }
-        buffer.push(c);
+        Ok(written)
     }
-    buffer
 }
 
-fn decompress(s: &str) -> String {
-    let mut result = String::new();
-    let mut chars = s.chars();
-    while let Some(c) = chars.next() {
-        if c == '(' {
-            let marker_str = try_get_marker(&mut chars);
-            if let Some((width, repeat)) = parse_marker(&marker_str) {
-                let mut buffer = String::with_capacity(width);
-                for _ in 0..width {
-                    if let Some(c) = chars.next() {
-                        buffer.push(c);
-                    }
+trait Length: Copy {
+    const ZERO: Self;
+    fn from_usize(n: usize) -> Self;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_length {
+    ($($t:ty),*) => {$(
+        impl Length for $t {
+            const ZERO: Self = 0;
+            fn from_usize(n: usize) -> Self {
+                n as Self
+            }
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$t>::checked_mul(self, other)
+            }
+        }
+    )*};
+}
+
+impl_length!(u64, u128);
+
+fn decoded_len<T: Length>(input: &[u8], version: Version, mode: Mode) -> Result<T, DecodeError> {
+    decoded_len_at(input, 0, true, version, mode)
+}
+
+fn decoded_len_at<T: Length>(
+    input: &[u8],
+    origin: usize,
+    top_level: bool,
+    version: Version,
+    mode: Mode,
+) -> Result<T, DecodeError> {
+    let add = |total: T, len: T| total.checked_add(len).ok_or(DecodeError::Overflow);
+    let mut total = T::ZERO;
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] != b'(' {
+            let skipped = mode == Mode::Strict && top_level && input[i].is_ascii_whitespace();
+            if !skipped {
+                total = add(total, T::from_usize(1))?;
+            }
+            i += 1;
+            continue;
+        }
+        let mut next = i + 1;
+        let read = read_marker(|| -> Result<_, Infallible> {
+            let byte = input.get(next).copied();
+            next += byte.is_some() as usize;
+            Ok(byte)
+        });
+        let (text, terminator) = match read {
+            Ok(read) => read,
+            Err(e) => match e {},
+        };
+        match scan_marker(mode, origin + i, text, terminator)? {
+            MarkerScan::Marker(marker) => {
+                let span = &input[next..next.saturating_add(marker.width).min(input.len())];
+                if mode == Mode::Strict && span.len() < marker.width {
+                    return Err(DecodeError::TruncatedSpan {
+                        offset: origin + next,
+                        width: marker.width,
+                        available: span.len(),
+                    });
                 }
-                result.push_str(&buffer.repeat(repeat));
-            } else {
-                result.push_str(&marker_str);
+                let span_len = match version {
+                    Version::V1 => T::from_usize(span.len()),
+                    Version::V2 => decoded_len_at(span, origin + next, false, version, mode)?,
+                };
+                let repeated = span_len
+                    .checked_mul(T::from_usize(marker.repeat))
+                    .ok_or(DecodeError::Overflow)?;
+                total = add(total, repeated)?;
+                i = next + span.len();
+            }
+            MarkerScan::Text(text) => {
+                total = add(total, T::from_usize(text.len()))?;
+                i = next;
             }
-        } else {
-            result.push(c);
         }
     }
+    Ok(total)
+}
 
-    result
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Strategy {
+    Greedy,
+    Shortest,
 }
 
-fn parse_marker(s: &str) -> Option<(usize, usize)> {
-    let mut parts = s.split('x');
-    if let Some(first) = parts.next() {
-        if let Some(second) = parts.next() {
-            if let Ok(first) = first.trim().parse::<usize>() {
-                if let Ok(second) = second.trim().parse::<usize>() {
-                    return Some((first, second));
+struct Encoder {
+    version: Version,
+    strategy: Strategy,
+    max_unit: usize,
+    memo: RefCell<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl Encoder {
+    const DEFAULT_MAX_UNIT: usize = 32;
+
+    pub(crate) fn new(version: Version, strategy: Strategy) -> Self {
+        Self {
+            version,
+            strategy,
+            max_unit: Self::DEFAULT_MAX_UNIT,
+            memo: RefCell::new(HashMap::new()),
+        }
+    }
+    pub(crate) fn encode(&self, text: &[u8]) -> Result<Vec<u8>> {
+        if self.version == Version::V2 {
+            if let Some(i) = text.iter().position(|&b| b == b'(') {
+                return Err(anyhow!("'(' at {} cannot be encoded under v2", i));
+            }
+        }
+        let mut encoded = self.encode_range(text);
+        let wrapped = if text.contains(&b'(') {
+            let mut wrapped = vec![];
+            push_marker(&mut wrapped, text, 1);
+            wrapped
+        } else {
+            text.to_vec()
+        };
+        if encoded.len() > wrapped.len() {
+            encoded = wrapped;
+        }
+        let mut decoded = Vec::with_capacity(text.len());
+        Decoder::new(&encoded[..], self.version)
+            .with_mode(Mode::Lenient)
+            .read_to_end(&mut decoded)?;
+        if decoded != text {
+            return Err(anyhow!("{:?} encoding does not round-trip", self.version));
+        }
+        Ok(encoded)
+    }
+    fn encode_range(&self, text: &[u8]) -> Vec<u8> {
+        match self.strategy {
+            Strategy::Greedy => self.encode_greedy(text),
+            Strategy::Shortest => self.encode_shortest(text),
+        }
+    }
+    fn encode_unit(&self, unit: &[u8]) -> Vec<u8> {
+        match self.version {
+            Version::V1 => unit.to_vec(),
+            Version::V2 => {
+                if let Some(encoded) = self.memo.borrow().get(unit) {
+                    return encoded.clone();
                 }
+                let encoded = self.encode_range(unit);
+                self.memo
+                    .borrow_mut()
+                    .insert(unit.to_vec(), encoded.clone());
+                encoded
             }
         }
     }
+    fn encode_greedy(&self, text: &[u8]) -> Vec<u8> {
+        let mut result = vec![];
+        let mut i = 0;
+        while i < text.len() {
+            let best = (1..=self.max_unit.min(text.len() - i))
+                .map(|width| (width, repeats(text, i, width)))
+                .filter(|&(width, repeat)| {
+                    let marked = marker_len(width, repeat) + width;
+                    marked < width * repeat || text[i] == b'('
+                })
+                .max_by_key(|&(width, repeat)| {
+                    (width * repeat) as isize - (marker_len(width, repeat) + width) as isize
+                });
+            match best {
+                Some((width, repeat)) => {
+                    push_marker(&mut result, &self.encode_unit(&text[i..i + width]), repeat);
+                    i += width * repeat;
+                }
+                None => {
+                    result.push(text[i]);
+                    i += 1;
+                }
+            }
+        }
+        result
+    }
+    fn encode_shortest(&self, text: &[u8]) -> Vec<u8> {
+        let n = text.len();
+        let mut cost = vec![usize::MAX; n + 1];
+        let mut choice: Vec<Option<(usize, usize)>> = vec![None; n + 1];
+        cost[n] = 0;
+        let mut spans: Vec<(usize, usize, VecDeque<usize>)> = (1..=n.to_string().len())
+            .map(|digits| {
+                let min_width = 10usize.pow(digits as u32 - 1);
+                (min_width, min_width * 10 - 1, VecDeque::new())
+            })
+            .collect();
+        for i in (0..n).rev() {
+            if text[i] != b'(' {
+                cost[i] = 1 + cost[i + 1];
+            }
+            let min_repeat = match self.version {
+                Version::V1 => 1,
+                Version::V2 => 2,
+            };
+            for width in 1..=self.max_unit.min(n - i) {
+                let max_repeat = repeats(text, i, width);
+                if max_repeat < min_repeat {
+                    continue;
+                }
+                let unit_len = self.encode_unit(&text[i..i + width]).len();
+                for repeat in min_repeat..=max_repeat {
+                    let next = cost[i + width * repeat];
+                    if next == usize::MAX {
+                        continue;
+                    }
+                    let total = marker_len(unit_len, repeat) + unit_len + next;
+                    if total < cost[i] {
+                        cost[i] = total;
+                        choice[i] = Some((width, repeat));
+                    }
+                }
+            }
+            if self.version == Version::V1 {
+                let end_cost = |j: usize| j + cost[j];
+                let mut best = (cost[i], choice[i]);
+                for (min_width, max_width, ends) in spans.iter_mut() {
+                    if i + *min_width <= n {
+                        let j = i + *min_width;
+                        while ends
+                            .back()
+                            .is_some_and(|&back| end_cost(back) >= end_cost(j))
+                        {
+                            ends.pop_back();
+                        }
+                        ends.push_back(j);
+                    }
+                    while ends.front().is_some_and(|&front| front > i + *max_width) {
+                        ends.pop_front();
+                    }
+                    if let Some(&j) = ends.front() {
+                        let total = marker_len(j - i, 1) + end_cost(j) - i;
+                        if total < best.0 {
+                            best = (total, Some((j - i, 1)));
+                        }
+                    }
+                }
+                (cost[i], choice[i]) = best;
+            }
+        }
+        let mut result = Vec::with_capacity(cost[0]);
+        let mut i = 0;
+        while i < n {
+            match choice[i] {
+                Some((width, repeat)) => {
+                    push_marker(&mut result, &self.encode_unit(&text[i..i + width]), repeat);
+                    i += width * repeat;
+                }
+                None => {
+                    result.push(text[i]);
+                    i += 1;
+                }
+            }
+        }
+        result
+    }
+}
+
+fn repeats(text: &[u8], i: usize, width: usize) -> usize {
+    let unit = &text[i..i + width];
+    text[i..]
+        .chunks_exact(width)
+        .take_while(|chunk| *chunk == unit)
+        .count()
+}
+
+fn marker_len(width: usize, repeat: usize) -> usize {
+    let digits = |n: usize| n.to_string().len();
+    3 + digits(width) + digits(repeat)
+}
 
-    None
+fn push_marker(result: &mut Vec<u8>, unit: &[u8], repeat: usize) {
+    result.extend_from_slice(format!("({}x{})", unit.len(), repeat).as_bytes());
+    result.extend_from_slice(unit);
 }
 
 fn _get_input() -> &'static str {
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
 
 pub(crate) fn run() {
     let _input = "value 5 goes to bot 2
@@ -15,118 +16,317 @@ value 2 goes to bot 2";
         .map(|i| (i.from_bot, i))
         .collect();
 
-    let _target = vec![5usize, 2];
-    let _target = vec![61usize, 17];
+    let _target = [5usize, 2];
+    let _target = [61usize, 17];
+    let dot_output: Option<&str> = None;
 
-    let mut factory = Factory::init(&init_instructions);
+    let cycles = find_cycles(&transfer_instructions);
+    if cycles.is_empty() {
+        println!("transfer rules contain no cycles");
+    }
+    for cycle in cycles.iter() {
+        let bots: Vec<String> = cycle.iter().map(|bot| format!("bot {}", bot)).collect();
+        println!("transfer rules form a loop: {}", bots.join(" -> "));
+    }
 
-    factory.instruct(&transfer_instructions);
-    println!("factory: {:?}\n", factory);
+    let mut factory = Factory::init(&init_instructions);
+    factory.simulate(&transfer_instructions);
+    for event in factory.events.iter() {
+        println!("{}", event);
+    }
+    for issue in factory.issues.iter() {
+        println!("issue: {}", issue);
+    }
+    if factory.has_stuck_bots() {
+        println!("some bots are stuck and cannot make progress");
+    }
+    println!();
 
-    let bot = factory.get_bot(&_target);
+    let bot = factory.who_compared(_target[0], _target[1]);
     println!("bot {:?} is responsible for comparing {:?}", bot, _target);
 
-    let outputs = &factory.outputs;
-    println!(
-        "outputs 0 * 1 * 2 = {}",
-        outputs.get(&0).unwrap() * outputs.get(&1).unwrap() * outputs.get(&2).unwrap()
-    );
+    if let Some(file_name) = dot_output {
+        std::fs::write(
+            file_name,
+            to_dot(&init_instructions, &transfer_instructions, &factory),
+        )
+        .expect("cannot write dot file");
+    }
+
+    let output = |i: usize| factory.output(i).unwrap();
+    println!("outputs 0 * 1 * 2 = {}", output(0) * output(1) * output(2));
 }
 
 #[derive(Debug)]
+struct Event {
+    bot: usize,
+    low: usize,
+    high: usize,
+    low_to: Destination,
+    high_to: Destination,
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "bot {} compares {} with {}: low to {}, high to {}",
+            self.bot, self.low, self.high, self.low_to, self.high_to
+        )
+    }
+}
+
+#[derive(Debug)]
+enum Issue {
+    MissingRule { bot: usize, values: [usize; 2] },
+    Starved { bot: usize, value: usize },
+    ExtraChip { bot: usize, value: usize },
+    Discarded { bot: usize, value: usize },
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::MissingRule { bot, values } => write!(
+                f,
+                "bot {} holds {} and {} but has no rule",
+                bot, values[0], values[1]
+            ),
+            Issue::Starved { bot, value } => {
+                write!(f, "bot {} is stuck holding only {}", bot, value)
+            }
+            Issue::ExtraChip { bot, value } => {
+                write!(f, "bot {} cannot take another chip {}", bot, value)
+            }
+            Issue::Discarded { bot, value } => write!(
+                f,
+                "bot {} has already fired, chip {} was discarded",
+                bot, value
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
 struct Factory {
     bots: HashMap<usize, Bot>,
-    outputs: HashMap<usize, usize>,
+    outputs: BTreeMap<usize, Vec<usize>>,
+    ready: BTreeSet<usize>,
+    events: Vec<Event>,
+    issues: Vec<Issue>,
 }
 
 impl Factory {
-    fn add_bot_value(&mut self, bot: usize, value: usize) {
-        self.bots.entry(bot).or_default().add_value(value)
-    }
-    fn set_output_value(&mut self, output: usize, value: usize) {
-        self.outputs.insert(output, value);
-    }
     pub(crate) fn init(init_instructions: &[InitInstruction]) -> Self {
-        let mut result = Self {
-            bots: Default::default(),
-            outputs: Default::default(),
-        };
+        let mut result = Self::default();
         for instruction in init_instructions {
-            result.add_bot_value(instruction.to_bot, instruction.value);
-            println!(
-                "after {:?}, bot {}: {:?}",
-                instruction,
-                instruction.to_bot,
-                result.bots.get(&instruction.to_bot)
-            );
+            result.give(Destination::Bot(instruction.to_bot), instruction.value);
         }
         result
     }
-    pub(crate) fn instruct(&mut self, instructions: &HashMap<usize, TransferInstruction>) {
-        while self.bots.iter().any(|(_, b)| b.is_ready()) {
-            let (bot_index, bot) = self
-                .bots
-                .iter_mut()
-                .filter(|(_, b)| b.is_ready())
-                .next()
-                .unwrap();
-            let values = if let Some(instruction) = instructions.get(bot_index) {
-                println!("instruction: {:?}", instruction);
-                bot.deliver_values(instruction)
-            } else {
-                continue;
-            };
-            for (destination, value) in values {
-                match destination {
-                    Destination::Bot(to_bot) => {
-                        self.add_bot_value(to_bot, value);
-                        println!("bot {}: {:?}", to_bot, self.bots.get(&to_bot));
-                    }
-                    Destination::Output(to_output) => {
-                        self.set_output_value(to_output, value);
-                    }
+    fn give(&mut self, destination: Destination, value: usize) {
+        match destination {
+            Destination::Bot(index) => {
+                let bot = self.bots.entry(index).or_default();
+                if bot.fired {
+                    self.issues.push(Issue::Discarded { bot: index, value });
+                    return;
+                }
+                if bot.values.len() == 2 {
+                    self.issues.push(Issue::ExtraChip { bot: index, value });
+                    return;
+                }
+                bot.values.push(value);
+                if bot.values.len() == 2 {
+                    self.ready.insert(index);
                 }
             }
+            Destination::Output(index) => self.outputs.entry(index).or_default().push(value),
         }
     }
-    pub(crate) fn get_bot(&self, target_values: &[usize]) -> Option<usize> {
-        let mut target = target_values.to_vec();
-        target.sort();
-        self.bots
+    pub(crate) fn simulate(&mut self, instructions: &HashMap<usize, TransferInstruction>) {
+        while let Some(index) = self.ready.pop_first() {
+            let bot = self.bots.get_mut(&index).unwrap();
+            let low = bot.values[0].min(bot.values[1]);
+            let high = bot.values[0].max(bot.values[1]);
+            let instruction = match instructions.get(&index) {
+                Some(instruction) => instruction,
+                None => {
+                    self.issues.push(Issue::MissingRule {
+                        bot: index,
+                        values: [low, high],
+                    });
+                    continue;
+                }
+            };
+            bot.values.clear();
+            bot.fired = true;
+            self.events.push(Event {
+                bot: index,
+                low,
+                high,
+                low_to: instruction.low_to,
+                high_to: instruction.high_to,
+            });
+            self.give(instruction.low_to, low);
+            self.give(instruction.high_to, high);
+        }
+        let mut waiting: Vec<_> = self
+            .bots
+            .iter()
+            .filter(|(_, bot)| bot.values.len() == 1)
+            .map(|(&bot, b)| (bot, b.values[0]))
+            .collect();
+        waiting.sort();
+        self.issues.extend(
+            waiting
+                .into_iter()
+                .map(|(bot, value)| Issue::Starved { bot, value }),
+        );
+    }
+    pub(crate) fn has_stuck_bots(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| matches!(issue, Issue::MissingRule { .. } | Issue::Starved { .. }))
+    }
+    pub(crate) fn who_compared(&self, a: usize, b: usize) -> Option<usize> {
+        let (low, high) = (a.min(b), a.max(b));
+        self.events
             .iter()
-            .filter(|&(_, bot)| bot.values == target)
-            .next()
-            .map(|(&i, _)| i)
+            .find(|event| event.low == low && event.high == high)
+            .map(|event| event.bot)
+    }
+    pub(crate) fn output(&self, index: usize) -> Option<usize> {
+        self.outputs.get(&index)?.first().copied()
     }
 }
 
-#[derive(Default, Debug)]
-struct Bot {
-    values: Vec<usize>,
-    is_done: bool,
-}
+fn to_dot(
+    init_instructions: &[InitInstruction],
+    instructions: &HashMap<usize, TransferInstruction>,
+    factory: &Factory,
+) -> String {
+    let node = |destination: &Destination| match destination {
+        Destination::Bot(index) => format!("bot{}", index),
+        Destination::Output(index) => format!("output{}", index),
+    };
+    let compared: HashMap<usize, &Event> = factory
+        .events
+        .iter()
+        .map(|event| (event.bot, event))
+        .collect();
 
-impl Bot {
-    pub(crate) fn add_value(&mut self, value: usize) {
-        self.values.push(value);
-        self.values.sort();
+    let mut dot = String::from("digraph factory {\n    rankdir=LR;\n");
+    for (i, instruction) in init_instructions.iter().enumerate() {
+        dot.push_str(&format!(
+            "    value{} [label=\"{}\", shape=circle];\n    value{} -> bot{};\n",
+            i, instruction.value, i, instruction.to_bot
+        ));
+    }
+    let mut bots: Vec<usize> = instructions
+        .keys()
+        .chain(factory.bots.keys())
+        .copied()
+        .collect();
+    bots.sort();
+    bots.dedup();
+    for bot in bots {
+        let label = match compared.get(&bot) {
+            Some(event) => format!("bot {}\\n{} < {}", bot, event.low, event.high),
+            None => format!("bot {}", bot),
+        };
+        dot.push_str(&format!("    bot{} [label=\"{}\"];\n", bot, label));
+    }
+    for (&output, values) in factory.outputs.iter() {
+        let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        dot.push_str(&format!(
+            "    output{} [label=\"output {}\\n{}\", shape=box];\n",
+            output,
+            output,
+            values.join(", ")
+        ));
     }
-    pub(crate) fn is_ready(&self) -> bool {
-        !self.is_done && self.values.len() == 2
+    let mut rules: Vec<&TransferInstruction> = instructions.values().collect();
+    rules.sort_by_key(|rule| rule.from_bot);
+    for rule in rules {
+        dot.push_str(&format!(
+            "    bot{} -> {} [label=\"low\", color=blue];\n",
+            rule.from_bot,
+            node(&rule.low_to)
+        ));
+        dot.push_str(&format!(
+            "    bot{} -> {} [label=\"high\", color=red];\n",
+            rule.from_bot,
+            node(&rule.high_to)
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn find_cycles(instructions: &HashMap<usize, TransferInstruction>) -> Vec<Vec<usize>> {
+    #[derive(Copy, Clone, Eq, PartialEq)]
+    enum State {
+        Visiting,
+        Done,
     }
-    pub(crate) fn deliver_values(
-        &mut self,
-        instruction: &TransferInstruction,
-    ) -> [(Destination, usize); 2] {
-        if !self.is_ready() {
-            panic!("bot is not ready to deliver");
+
+    let successors = |bot: usize| -> Vec<usize> {
+        instructions
+            .get(&bot)
+            .map(|rule| {
+                [rule.low_to, rule.high_to]
+                    .into_iter()
+                    .filter_map(|destination| match destination {
+                        Destination::Bot(index) => Some(index),
+                        Destination::Output(_) => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut starts: Vec<usize> = instructions.keys().copied().collect();
+    starts.sort();
+    let mut state: HashMap<usize, State> = HashMap::new();
+    let mut cycles = vec![];
+    for start in starts {
+        if state.contains_key(&start) {
+            continue;
+        }
+        let mut path = vec![start];
+        let mut stack = vec![successors(start).into_iter()];
+        state.insert(start, State::Visiting);
+        while let Some(next) = stack.last_mut() {
+            match next.next() {
+                Some(bot) => match state.get(&bot) {
+                    Some(State::Visiting) => {
+                        let from = path.iter().position(|&b| b == bot).unwrap();
+                        let mut cycle = path[from..].to_vec();
+                        cycle.push(bot);
+                        cycles.push(cycle);
+                    }
+                    Some(State::Done) => {}
+                    None => {
+                        state.insert(bot, State::Visiting);
+                        path.push(bot);
+                        stack.push(successors(bot).into_iter());
+                    }
+                },
+                None => {
+                    stack.pop();
+                    state.insert(path.pop().unwrap(), State::Done);
+                }
+            }
         }
-        self.is_done = true;
-        [
-            (instruction.low_to, self.values[0]),
-            (instruction.high_to, self.values[1]),
-        ]
     }
+    cycles
+}
+
+#[derive(Default, Debug)]
+struct Bot {
+    values: Vec<usize>,
+    fired: bool,
 }
 
 #[derive(Debug)]
@@ -148,6 +348,15 @@ enum Destination {
     Output(usize),
 }
 
+impl Display for Destination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Destination::Bot(index) => write!(f, "bot {}", index),
+            Destination::Output(index) => write!(f, "output {}", index),
+        }
+    }
+}
+
 fn parse_instructions(s: &str) -> (Vec<InitInstruction>, Vec<TransferInstruction>) {
     let mut init_instructions: Vec<InitInstruction> = Default::default();
     let mut transfer_instructions: Vec<TransferInstruction> = Default::default();
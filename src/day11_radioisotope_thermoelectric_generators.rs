@@ -1,9 +1,14 @@
+use anyhow::{anyhow, Result};
+use log::warn;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
+use std::io::{IsTerminal, Write};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
 use utils::a_star::*;
 
-const NUM_FLOORS: usize = 4;
-
 pub(crate) fn run() {
     let _input = "The first floor contains a hydrogen-compatible microchip and a lithium-compatible microchip.
 The second floor contains a hydrogen generator.
@@ -11,121 +16,434 @@ The third floor contains a lithium generator.
 The fourth floor contains nothing relevant.";
     let _input = _get_input();
 
-    let mut building: Building = _input.into();
-    add_extra_items_on_first_floor(&mut building, true);
+    let elevator_capacity = 2;
+    let default_extra_elements = "elerium,dilithium";
 
-    let target = {
-        let mut last_floor = building
-            .floors
-            .iter()
-            .flat_map(|f| f.iter().cloned())
-            .collect::<Vec<_>>();
-        last_floor.sort();
+    let extra_elements: Vec<String> = std::env::args()
+        .nth(2)
+        .unwrap_or_else(|| default_extra_elements.to_string())
+        .split(',')
+        .map(|element| element.trim().to_string())
+        .filter(|element| !element.is_empty())
+        .collect();
 
-        Building {
-            elevator_floor: 3,
-            floors: [
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                last_floor,
-            ],
-        }
-    };
-    println!("target state:{}", target);
+    let mut building: Building = _input.parse().unwrap();
+    add_extra_items(&mut building, &extra_elements, 0);
+
+    let strategy = Strategy::AStar;
+    let compare_strategies = true;
+    let replay = Replay::Print;
+    // let replay = Replay::Animate { delay: Duration::from_millis(300) };
+    // let replay = Replay::Interactive;
+
+    println!("target state:{}", building.target());
 
     println!("initial state:{}\n", building);
 
-    let solution = a_star_search(
-        building,
-        get_successors,
-        distance_function,
-        |left| left == &target,
-        None,
-    )
-    .expect("no solution found")
-    .shortest_path;
-    for (i, step) in solution.iter().enumerate().skip(1) {
-        println!("step {}:{}\n", i, step);
+    let solution = strategy.solve(&building, elevator_capacity).unwrap();
+    let moves = validate_path(&solution.path, elevator_capacity).unwrap();
+    replay.show(&solution.path, &moves).unwrap();
+    println!("{} steps needed", solution.steps());
+
+    if compare_strategies {
+        println!(
+            "\n{:<20} {:>6} {:>10} {:>10}",
+            "strategy", "steps", "expanded", "time"
+        );
+        for strategy in Strategy::ALL {
+            let solution = strategy.solve(&building, elevator_capacity).unwrap();
+            println!(
+                "{:<20} {:>6} {:>10} {:>8}ms",
+                strategy.to_string(),
+                solution.steps(),
+                solution.expanded,
+                solution.elapsed.as_millis()
+            );
+        }
     }
-    println!("{} steps needed", solution.len() - 1);
 }
 
-fn add_extra_items_on_first_floor(building: &mut Building, add: bool) {
-    if !add {
-        return;
+#[allow(unused)]
+enum Replay {
+    Print,
+    Animate { delay: Duration },
+    Interactive,
+}
+
+impl Replay {
+    fn show(&self, path: &[Building], moves: &[Move]) -> Result<()> {
+        let replay = match self {
+            Replay::Animate { .. } if !std::io::stdout().is_terminal() => {
+                warn!("stdout is not a terminal, printing steps instead");
+                &Replay::Print
+            }
+            Replay::Interactive if !std::io::stdin().is_terminal() => {
+                warn!("stdin is not a terminal, printing steps instead");
+                &Replay::Print
+            }
+            replay => replay,
+        };
+        if let Replay::Print = replay {
+            for (i, (step, moved)) in path.iter().skip(1).zip(moves).enumerate() {
+                println!("step {}: {}{}\n", i + 1, moved, step);
+            }
+            return Ok(());
+        }
+
+        print!("\x1b[?25l\x1b[2J");
+        let mut line = String::new();
+        for (i, step) in path.iter().enumerate() {
+            print!("\x1b[H\x1b[J");
+            match i.checked_sub(1) {
+                None => println!("initial state:{}", step),
+                Some(previous) => {
+                    println!("step {}/{}: {}{}", i, moves.len(), moves[previous], step)
+                }
+            }
+            match replay {
+                Replay::Animate { delay } => {
+                    std::io::stdout().flush()?;
+                    thread::sleep(*delay);
+                }
+                _ => {
+                    print!("press enter for the next step, q to stop ");
+                    std::io::stdout().flush()?;
+                    line.clear();
+                    std::io::stdin().read_line(&mut line)?;
+                    if line.trim() == "q" {
+                        break;
+                    }
+                }
+            }
+        }
+        println!("\x1b[?25h");
+        Ok(())
+    }
+}
+
+struct Move {
+    from: usize,
+    to: usize,
+    items: Vec<Device>,
+}
+
+impl Move {
+    fn between(before: &Building, after: &Building) -> Option<Self> {
+        let (from, to) = (before.elevator_floor, after.elevator_floor);
+        if from.abs_diff(to) != 1 || to >= before.floors.len() {
+            return None;
+        }
+        let items = before.floors[from]
+            .iter()
+            .filter(|device| !after.floors[from].contains(device))
+            .cloned()
+            .collect();
+        Some(Self { from, to, items })
     }
-    building.floors[0].extend([
-        Device::Generator("elerium"),
-        Device::Microchip("elerium"),
-        Device::Generator("dilithium"),
-        Device::Microchip("dilithium"),
-    ])
 }
 
-impl Node for Building {}
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "elevator {} with {}",
+            if self.to > self.from { "up" } else { "down" },
+            self.items
+                .iter()
+                .map(|device| device.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
 
-fn get_limited_items(from: &[Device]) -> Option<Vec<Device>> {
-    if from.len() < 5 {
-        return None;
+fn validate_path(path: &[Building], capacity: usize) -> Result<Vec<Move>> {
+    if let Some(start) = path.first() {
+        if !start.is_valid() {
+            return Err(anyhow!("path starts in a state that fries a microchip"));
+        }
     }
-    let mut map: HashMap<&str, Vec<&Device>> = Default::default();
-    for device in from {
-        let name = device.get_name();
-        map.entry(name).or_default().push(device);
+    let moves = path
+        .windows(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            let step = i + 1;
+            let (before, after) = (&pair[0], &pair[1]);
+            let moved = Move::between(before, after).ok_or_else(|| {
+                anyhow!("step {}: elevator did not move to an adjacent floor", step)
+            })?;
+            if moved.items.is_empty() || moved.items.len() > capacity {
+                return Err(anyhow!(
+                    "step {}: elevator carries {} items, capacity is {}",
+                    step,
+                    moved.items.len(),
+                    capacity
+                ));
+            }
+            let items: Vec<&Device> = moved.items.iter().collect();
+            if !before.is_valid_bringing(&items, moved.to) {
+                return Err(anyhow!("step {}: {} fries a microchip", step, moved));
+            }
+            let mut expected = before.clone();
+            expected.bring(moved.items.clone(), moved.to);
+            if expected.floors != after.floors {
+                return Err(anyhow!(
+                    "step {}: floors do not match the items moved by the elevator",
+                    step
+                ));
+            }
+            Ok(moved)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    match path.last() {
+        Some(last) if *last == path[0].target() => Ok(moves),
+        _ => Err(anyhow!(
+            "path does not end with every item on the top floor"
+        )),
     }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Strategy {
+    Bfs,
+    BidirectionalBfs,
+    AStar,
+}
+
+impl Strategy {
+    const ALL: [Strategy; 3] = [Strategy::Bfs, Strategy::BidirectionalBfs, Strategy::AStar];
 
-    let mut count_both = 0usize;
-    Some(
-        map.into_iter()
-            .flat_map(|(_, devices)| {
-                if devices.len() == 2 {
-                    if count_both < 2 {
-                        count_both += 1;
-                        devices
+    fn solve(&self, start: &Building, capacity: usize) -> Result<Solution> {
+        if !start.is_valid() {
+            return Err(anyhow!("initial state already fries a microchip:{}", start));
+        }
+        let started = Instant::now();
+        let target = start.target();
+        let (path, expanded) = match self {
+            Strategy::Bfs => {
+                let mut forward = Frontier::new(start.clone());
+                loop {
+                    if let Some(&end) = forward.seen.get(&target.canonical_key()) {
+                        break (forward.path_to(end), forward.expanded);
+                    }
+                    if forward.expand(capacity, |_| {}) == 0 {
+                        return Err(anyhow!("no solution found"));
+                    }
+                }
+            }
+            Strategy::BidirectionalBfs => {
+                let mut forward = Frontier::new(start.clone());
+                let mut backward = Frontier::new(target.clone());
+                if forward.seen.contains_key(&target.canonical_key()) {
+                    return Ok(Solution {
+                        path: vec![start.clone()],
+                        expanded: 0,
+                        elapsed: started.elapsed(),
+                    });
+                }
+                loop {
+                    let forward_turn = forward.frontier.len() <= backward.frontier.len();
+                    let (side, other) = if forward_turn {
+                        (&mut forward, &backward)
                     } else {
-                        Default::default()
+                        (&mut backward, &forward)
+                    };
+                    let mut best: Option<(usize, usize, usize)> = None;
+                    let added = side.expand(capacity, |(index, node)| {
+                        if let Some(&other_index) = other.seen.get(&node.state.canonical_key()) {
+                            let steps = node.depth + other.nodes[other_index].depth;
+                            if best.map(|(_, _, best)| steps < best).unwrap_or(true) {
+                                best = Some((index, other_index, steps));
+                            }
+                        }
+                    });
+                    if let Some((index, other_index, _)) = best {
+                        let (forward_index, backward_index) = if forward_turn {
+                            (index, other_index)
+                        } else {
+                            (other_index, index)
+                        };
+                        let mut path = forward.path_to(forward_index);
+                        let meeting = path.last().unwrap();
+                        let mut rest = backward.path_to(backward_index);
+                        let names = rest.last().unwrap().name_mapping_to(meeting);
+                        rest.pop();
+                        path.extend(rest.into_iter().rev().map(|state| state.renamed(&names)));
+                        break (path, forward.expanded + backward.expanded);
+                    }
+                    if added == 0 {
+                        return Err(anyhow!("no solution found"));
                     }
-                } else {
-                    devices
                 }
-            })
-            .cloned()
-            .collect(),
-    )
+            }
+            Strategy::AStar => {
+                let expanded = Cell::new(0usize);
+                let states = RefCell::new(HashMap::from([(start.canonical_key(), start.clone())]));
+                let target_key = target.canonical_key();
+                let keys = a_star_search(
+                    start.canonical_key(),
+                    |key| {
+                        expanded.set(expanded.get() + 1);
+                        let state = states.borrow()[key].clone();
+                        get_successors(&state, capacity)
+                            .into_iter()
+                            .map(|successor| {
+                                let key = successor.node.canonical_key();
+                                states
+                                    .borrow_mut()
+                                    .entry(key.clone())
+                                    .or_insert(successor.node);
+                                Successor::new(key, successor.cost)
+                            })
+                            .collect()
+                    },
+                    |details| lower_bound(&states.borrow()[details.current_node], capacity),
+                    |key| key == &target_key,
+                    Some(&Options::default().with_no_logs()),
+                )
+                .map_err(|e| anyhow!("no solution found: {:?}", e))?
+                .shortest_path;
+                let path = rebuild_path(start, &keys, capacity)
+                    .ok_or_else(|| anyhow!("a* keys do not form a path of real moves"))?;
+                (path, expanded.get())
+            }
+        };
+        Ok(Solution {
+            path,
+            expanded,
+            elapsed: started.elapsed(),
+        })
+    }
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strategy::Bfs => write!(f, "bfs"),
+            Strategy::BidirectionalBfs => write!(f, "bidirectional bfs"),
+            Strategy::AStar => write!(f, "a*"),
+        }
+    }
+}
+
+struct Solution {
+    path: Vec<Building>,
+    expanded: usize,
+    elapsed: Duration,
 }
 
-fn get_successors(state: &Building) -> Vec<Successor<Building, i32>> {
-    let mut items = &state.floors[state.elevator_floor];
-    let limited_items = get_limited_items(items);
-    if let Some(value) = &limited_items {
-        items = value;
+impl Solution {
+    fn steps(&self) -> usize {
+        self.path.len() - 1
+    }
+}
+
+fn rebuild_path(start: &Building, keys: &[CanonicalKey], capacity: usize) -> Option<Vec<Building>> {
+    let mut path = vec![start.clone()];
+    for key in keys.iter().skip(1) {
+        let next = get_successors(path.last().unwrap(), capacity)
+            .into_iter()
+            .map(|successor| successor.node)
+            .find(|state| &state.canonical_key() == key)?;
+        path.push(next);
+    }
+    Some(path)
+}
+
+struct SearchNode {
+    state: Building,
+    parent: Option<usize>,
+    depth: usize,
+}
+
+struct Frontier {
+    nodes: Vec<SearchNode>,
+    seen: HashMap<CanonicalKey, usize>,
+    frontier: Vec<usize>,
+    expanded: usize,
+}
+
+impl Frontier {
+    fn new(start: Building) -> Self {
+        Self {
+            seen: HashMap::from([(start.canonical_key(), 0)]),
+            nodes: vec![SearchNode {
+                state: start,
+                parent: None,
+                depth: 0,
+            }],
+            frontier: vec![0],
+            expanded: 0,
+        }
+    }
+    fn expand(
+        &mut self,
+        capacity: usize,
+        mut on_reached: impl FnMut((usize, &SearchNode)),
+    ) -> usize {
+        let mut next_frontier = vec![];
+        for parent in std::mem::take(&mut self.frontier) {
+            self.expanded += 1;
+            let depth = self.nodes[parent].depth + 1;
+            for successor in get_successors(&self.nodes[parent].state, capacity) {
+                let key = successor.node.canonical_key();
+                if self.seen.contains_key(&key) {
+                    continue;
+                }
+                let index = self.nodes.len();
+                self.seen.insert(key, index);
+                self.nodes.push(SearchNode {
+                    state: successor.node,
+                    parent: Some(parent),
+                    depth,
+                });
+                on_reached((index, &self.nodes[index]));
+                next_frontier.push(index);
+            }
+        }
+        self.frontier = next_frontier;
+        self.frontier.len()
+    }
+    fn path_to(&self, index: usize) -> Vec<Building> {
+        let mut path = vec![];
+        let mut current = Some(index);
+        while let Some(i) = current {
+            path.push(self.nodes[i].state.clone());
+            current = self.nodes[i].parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+fn add_extra_items(building: &mut Building, elements: &[String], floor: usize) {
+    for element in elements {
+        building.floors[floor].extend([
+            Device::Generator(element.clone()),
+            Device::Microchip(element.clone()),
+        ]);
+    }
+    building.floors[floor].sort();
+}
+
+impl Node for CanonicalKey {}
+
+fn get_successors(state: &Building, capacity: usize) -> Vec<Successor<Building, i32>> {
+    let items = &state.floors[state.elevator_floor];
+
+    let mut possible_floors = vec![];
+    if state.elevator_floor > 0 {
+        possible_floors.push(state.elevator_floor - 1);
+    }
+    if state.elevator_floor + 1 < state.floors.len() {
+        possible_floors.push(state.elevator_floor + 1);
     }
-    let items = items;
-
-    let possible_floors = if state.elevator_floor == NUM_FLOORS - 1 {
-        vec![state.elevator_floor - 1]
-    } else if state.elevator_floor == 0 {
-        vec![1]
-    } else {
-        vec![state.elevator_floor - 1, state.elevator_floor + 1]
-    };
     possible_floors
         .into_iter()
         .flat_map(|to_floor| {
-            if items.len() < 2 {
-                return items
-                    .into_iter()
-                    .filter(|&item| state.is_valid_bringing(&[item], to_floor))
-                    .map(|item| {
-                        let mut next = state.clone();
-                        next.bring(vec![item.clone()], to_floor);
-                        Successor::new(next, 1)
-                    })
-                    .collect::<Vec<_>>();
-            }
-            permutator::Combination::combination(items, 2)
-                .chain(items.iter().map(|item| vec![item]))
+            (1..=capacity.min(items.len()))
+                .rev()
+                .flat_map(|count| permutator::Combination::combination(items, count))
                 .filter(move |c| state.is_valid_bringing(&c[..], to_floor))
                 .map(move |c| {
                     let mut next = state.clone();
@@ -137,32 +455,39 @@ fn get_successors(state: &Building) -> Vec<Successor<Building, i32>> {
         .collect()
 }
 
-fn distance_function(details: CurrentNodeDetails<Building, i32>) -> i32 {
-    let n = details.current_node.floors.len();
-    10 * (0..n - 1).fold(0i32, |distance, i| {
-        let count = details.current_node.floors[i].len();
-        distance + (count * (n - i - 1)) as i32
-    }) + details
-        .current_node
-        .floors
-        .iter()
-        .enumerate()
-        .fold(HashMap::new(), |mut map, (i, devices)| {
-            for device in devices {
-                let entry: &mut usize = map.entry(device.get_name()).or_default();
-                *entry = (*entry).max(i) - (*entry).min(i);
+fn lower_bound(state: &Building, capacity: usize) -> i32 {
+    let mut below = 0;
+    (0..state.floors.len() - 1)
+        .map(|i| {
+            below += state.floors[i].len();
+            if below == 0 {
+                return 0;
+            }
+            let trips_up = if capacity < 2 {
+                below
+            } else {
+                (below - 1).div_ceil(capacity - 1).max(1)
+            };
+            let moves = 2 * trips_up - 1;
+            if state.elevator_floor > i {
+                moves as i32 + 1
+            } else {
+                moves as i32
             }
-            map
         })
-        .into_iter()
-        .map(|(_, diff)| diff as i32)
-        .sum::<i32>()
+        .sum()
 }
 
 #[derive(Clone, Hash, Ord, PartialOrd, Eq, PartialEq, Debug)]
 struct Building {
     elevator_floor: usize,
-    floors: [Vec<Device>; NUM_FLOORS],
+    floors: Vec<Vec<Device>>,
+}
+
+#[derive(Clone, Hash, Eq, PartialEq, Debug)]
+enum CanonicalKey {
+    Packed(u128),
+    Floors(Vec<usize>),
 }
 
 impl Display for Building {
@@ -183,12 +508,7 @@ impl Display for Building {
                     .into_iter()
                     .map(|space| match space {
                         None => ". ".to_string(),
-                        Some(device) => match device {
-                            Device::Generator(g) =>
-                                format!("{}G", g.chars().next().unwrap().to_uppercase()),
-                            Device::Microchip(m) =>
-                                format!("{}M", m.chars().next().unwrap().to_uppercase()),
-                        },
+                        Some(device) => device.to_string(),
                     })
                     .collect::<Vec<String>>()
                     .join(" ")
@@ -199,6 +519,98 @@ impl Display for Building {
 }
 
 impl Building {
+    pub(crate) fn is_valid(&self) -> bool {
+        self.floors.iter().all(|floor| {
+            !floor
+                .iter()
+                .any(|device| matches!(device, Device::Generator(_)))
+                || floor.iter().all(|device| match device {
+                    Device::Microchip(name) => floor.contains(&Device::Generator(name.clone())),
+                    Device::Generator(_) => true,
+                })
+        })
+    }
+    fn element_floors(&self) -> Vec<(usize, usize, &str)> {
+        let missing = self.floors.len();
+        let mut pairs: Vec<(usize, usize, &str)> = vec![];
+        for (i, floor) in self.floors.iter().enumerate() {
+            for device in floor {
+                let name = device.get_name();
+                let index = match pairs.iter().position(|pair| pair.2 == name) {
+                    Some(index) => index,
+                    None => {
+                        pairs.push((missing, missing, name));
+                        pairs.len() - 1
+                    }
+                };
+                match device {
+                    Device::Generator(_) => pairs[index].0 = i,
+                    Device::Microchip(_) => pairs[index].1 = i,
+                }
+            }
+        }
+        pairs.sort();
+        pairs
+    }
+    pub(crate) fn canonical_key(&self) -> CanonicalKey {
+        let floors: Vec<usize> = std::iter::once(self.elevator_floor)
+            .chain(
+                self.element_floors()
+                    .into_iter()
+                    .flat_map(|(generator, chip, _)| [generator, chip]),
+            )
+            .collect();
+
+        let bits = usize::BITS - self.floors.len().leading_zeros();
+        if floors.len() * bits as usize > u128::BITS as usize {
+            return CanonicalKey::Floors(floors);
+        }
+        CanonicalKey::Packed(
+            floors
+                .into_iter()
+                .fold(0, |key, floor| (key << bits) | floor as u128),
+        )
+    }
+    pub(crate) fn name_mapping_to(&self, other: &Building) -> HashMap<String, String> {
+        self.element_floors()
+            .into_iter()
+            .zip(other.element_floors())
+            .map(|((_, _, from), (_, _, to))| (from.to_string(), to.to_string()))
+            .collect()
+    }
+    pub(crate) fn renamed(&self, names: &HashMap<String, String>) -> Self {
+        let rename = |name: &String| names.get(name).unwrap_or(name).clone();
+        Self {
+            elevator_floor: self.elevator_floor,
+            floors: self
+                .floors
+                .iter()
+                .map(|floor| {
+                    let mut devices: Vec<Device> = floor
+                        .iter()
+                        .map(|device| match device {
+                            Device::Generator(name) => Device::Generator(rename(name)),
+                            Device::Microchip(name) => Device::Microchip(rename(name)),
+                        })
+                        .collect();
+                    devices.sort();
+                    devices
+                })
+                .collect(),
+        }
+    }
+    pub(crate) fn target(&self) -> Self {
+        let mut last_floor: Vec<Device> = self.floors.iter().flatten().cloned().collect();
+        last_floor.sort();
+
+        let mut floors = vec![Vec::new(); self.floors.len()];
+        let top = floors.len() - 1;
+        floors[top] = last_floor;
+        Self {
+            elevator_floor: top,
+            floors,
+        }
+    }
     pub(crate) fn is_valid_bringing(&self, items: &[&Device], to_floor: usize) -> bool {
         let floor = &self.floors[to_floor];
         let from_floor = &self.floors[self.elevator_floor];
@@ -208,7 +620,7 @@ impl Building {
                     if floor.iter().any(|device| {
                         if let Device::Microchip(name) = device {
                             if name != generator {
-                                let other_generator = Device::Generator(name);
+                                let other_generator = Device::Generator(name.clone());
                                 if !items.contains(&&other_generator)
                                     && !floor.contains(&other_generator)
                                 {
@@ -220,7 +632,7 @@ impl Building {
                     }) {
                         return false;
                     }
-                    let chip = Device::Microchip(generator);
+                    let chip = Device::Microchip(generator.clone());
                     if !items.contains(&&chip) && from_floor.contains(&chip) {
                         if from_floor
                             .iter()
@@ -238,7 +650,7 @@ impl Building {
                     }
                 }
                 Device::Microchip(chip) => {
-                    let generator = Device::Generator(chip);
+                    let generator = Device::Generator(chip.clone());
                     //                     println!("\
                     // chip: {chip:?}
                     // generator: {generator:?}
@@ -271,11 +683,10 @@ impl Building {
         self.floors[to_floor].sort();
         self.elevator_floor = to_floor;
     }
-    pub(crate) fn get_ordered_row_contents(&self) -> [Vec<Option<&Device>>; NUM_FLOORS] {
+    pub(crate) fn get_ordered_row_contents(&self) -> Vec<Vec<Option<&Device>>> {
         let mut all: Vec<_> = self.floors.iter().flat_map(|f| f.iter()).collect();
         all.sort();
-        let result: Vec<_> = self
-            .floors
+        self.floors
             .iter()
             .map(|f| {
                 all.iter()
@@ -288,21 +699,22 @@ impl Building {
                     })
                     .collect::<Vec<Option<&Device>>>()
             })
-            .collect();
-        result.try_into().unwrap()
+            .collect()
     }
 }
 
-impl From<&'static str> for Building {
-    fn from(s: &'static str) -> Self {
-        let clean_word = |word: &'static str| word.split('-').next().unwrap();
-        let parse_floor = |floor: &'static str| {
+impl FromStr for Building {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let clean_word = |word: &str| word.split('-').next().unwrap().to_string();
+        let parse_floor = |floor: &str| {
             let mut devices: Vec<Device> = Default::default();
             let mut last_word = "";
             for word in floor.split_whitespace() {
                 match word.trim_end_matches(&[',', '.']) {
                     "generator" => {
-                        devices.push(Device::Generator(last_word));
+                        devices.push(Device::Generator(last_word.to_string()));
                     }
                     "microchip" => {
                         devices.push(Device::Microchip(clean_word(last_word)));
@@ -315,22 +727,30 @@ impl From<&'static str> for Building {
             devices.sort();
             devices
         };
-        Self {
-            elevator_floor: 0,
-            floors: s
-                .lines()
-                .map(|line| parse_floor(line))
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap(),
+        let floors: Vec<Vec<Device>> = s.lines().map(parse_floor).collect();
+        if floors.is_empty() {
+            return Err(anyhow!("building has no floors"));
         }
+        Ok(Self {
+            elevator_floor: 0,
+            floors,
+        })
     }
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 enum Device {
-    Generator(&'static str),
-    Microchip(&'static str),
+    Generator(String),
+    Microchip(String),
+}
+
+impl Display for Device {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Device::Generator(g) => write!(f, "{}G", g.chars().next().unwrap().to_uppercase()),
+            Device::Microchip(m) => write!(f, "{}M", m.chars().next().unwrap().to_uppercase()),
+        }
+    }
 }
 
 impl Device {
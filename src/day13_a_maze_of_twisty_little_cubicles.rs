@@ -1,10 +1,11 @@
+use crate::grid::{Bounds, Point};
 use std::collections::{HashMap, HashSet};
 use utils::a_star::*;
 
 pub(crate) fn run() {
-    let start = Coord { x: 1, y: 1 };
-    // let end = Coord { x: 7, y: 4 };
-    let end = Coord { x: 31, y: 39 };
+    let start = Point::new(1, 1);
+    // let end = Point::new(7, 4);
+    let end = Point::new(31, 39);
 
     let solution = a_star_search(
         start,
@@ -23,24 +24,24 @@ pub(crate) fn run() {
         solution.len() - 1
     );
 
-    let mut visited: HashMap<Coord, usize> = Default::default();
-    let start = Coord { x: 1, y: 1 };
+    let mut visited: HashMap<Point, usize> = Default::default();
+    let start = Point::new(1, 1);
     let steps = 50usize;
-    visited.insert(start.clone(), steps);
+    visited.insert(start, steps);
     locations_can_be_visited_in_steps(&start, &mut visited, steps - 1);
     println!("{} locations can be visited in 50 steps", visited.len());
 }
 
 fn locations_can_be_visited_in_steps(
-    from: &Coord,
-    visited: &mut HashMap<Coord, usize>,
+    from: &Point,
+    visited: &mut HashMap<Point, usize>,
     steps_remaining: usize,
 ) {
     for successor in get_successors_internal(from)
         .into_iter()
-        .filter(|coord| !Maze::is_wall(coord))
+        .filter(|coord| Maze.contains(coord))
     {
-        let entry = visited.entry(successor.clone()).or_default();
+        let entry = visited.entry(successor).or_default();
         if steps_remaining > *entry {
             *entry = steps_remaining;
             if steps_remaining > 0 {
@@ -51,30 +52,33 @@ fn locations_can_be_visited_in_steps(
     }
 }
 
-fn get_successors_internal(current: &Coord) -> Vec<Coord> {
-    (current.y.max(1) - 1..=current.y + 1)
-        .map(move |y| Coord { x: current.x, y })
-        .chain((current.x.max(1) - 1..=current.x + 1).map(move |x| Coord { x, y: current.y }))
-        .filter(|pos| pos != current && !Maze::is_wall(&pos))
+fn get_successors_internal(current: &Point) -> Vec<Point> {
+    current
+        .neighbors()
+        .filter(|pos| Maze.contains(pos))
         .collect()
 }
 
-fn get_successors(current: &Coord) -> Vec<Successor<Coord, i32>> {
+fn get_successors(current: &Point) -> Vec<Successor<Point, i32>> {
     get_successors_internal(current)
         .into_iter()
         .map(|pos| Successor::new(pos, 1))
         .collect()
 }
 
-fn distance_function(details: CurrentNodeDetails<Coord, i32>, end: &Coord) -> i32 {
+fn distance_function(details: CurrentNodeDetails<Point, i32>, end: &Point) -> i32 {
     details
         .current_node
         .manhattan_distance(end) as i32
 }
 
-impl Node for Coord {}
+struct Maze;
 
-struct Maze {}
+impl Bounds for Maze {
+    fn contains(&self, point: &Point) -> bool {
+        point.x >= 0 && point.y >= 0 && !Self::is_wall(point)
+    }
+}
 
 impl Maze {
     // const FAV_NUMBER: usize = 10;
@@ -82,21 +86,21 @@ impl Maze {
     const WIDTH: usize = 32;
     const HEIGHT: usize = 40;
 
-    fn is_wall(coord: &Coord) -> bool {
-        let x = coord.x;
-        let y = coord.y;
+    fn is_wall(coord: &Point) -> bool {
+        let x = coord.x as usize;
+        let y = coord.y as usize;
         let num = x * x + 3 * x + 2 * x * y + y + y * y + Self::FAV_NUMBER;
         let bits = get_count_set_bits(num);
         bits % 2 == 1
     }
-    fn print(with_path: &[Coord]) {
+    fn print(with_path: &[Point]) {
         let path: HashSet<_> = with_path.into_iter().collect();
         for row in 0..Self::HEIGHT {
             println!(
                 "{}",
                 (0..Self::WIDTH)
                     .map(|col| {
-                        let coord = Coord { x: col, y: row };
+                        let coord = Point::new(col as i64, row as i64);
                         if path.contains(&coord) {
                             'O'
                         } else if Self::is_wall(&coord) {
@@ -111,18 +115,6 @@ impl Maze {
     }
 }
 
-#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Debug, Clone)]
-struct Coord {
-    x: usize,
-    y: usize,
-}
-
-impl Coord {
-    pub(crate) fn manhattan_distance(&self, other: &Self) -> usize {
-        (self.x).abs_diff(other.x) + (self.y).abs_diff(other.y)
-    }
-}
-
 fn get_count_set_bits(mut number: usize) -> usize {
     let n = (number as f64).log2().floor() as u32;
     let mut count = 0usize;
@@ -1,129 +1,467 @@
-use std::collections::HashSet;
+use crate::checkpoint::{Checkpoint, CheckpointFile};
+use crate::md5_mining::{hex_char, nibble, Digest, HashAlgorithm, Miner};
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
 
 pub(crate) fn run() {
     let _input = "abc";
     let _input = "cuanljph";
 
-    let mut computer = Computer::new(_input);
+    let checkpoint_files: Option<[&str; 2]> = None;
+    // let checkpoint_files: Option<[&str; 2]> =
+    //     Some(["day14_checkpoint.txt", "day14_stretched_checkpoint.txt"]);
+    let cache_dir: Option<&str> = None;
+    // let cache_dir: Option<&str> = Some("day14_cache");
+
     let n = 64usize;
-    computer.get_keys(n, false);
-    // println!("{:?}", computer.keys.iter().filter(|k| k.resolved).map(|k| k.index).collect::<Vec<_>>());
+    let rules = KeyRules::default();
+    let mut computer = Computer::new(_input, rules, cache_dir).unwrap();
+    if let Some([path, _]) = checkpoint_files {
+        computer = computer.with_checkpoint(CheckpointFile::new(path));
+    }
+    let key = computer.get_key(n).unwrap();
+    // println!("{:?}", computer.keys.iter().map(|k| k.index).collect::<Vec<_>>());
     println!(
         "Using salt of {} index {} produces the {}th key",
-        computer.salt,
-        computer.keys.iter().nth(n - 1).unwrap().index,
-        n
+        computer.salt, key.index, n
     );
     println!("stretching the key:");
-    let mut computer = Computer::new(_input);
-    computer.get_keys(n, true);
+    let rules = KeyRules {
+        stretch_rounds: KeyRules::STRETCH_ROUNDS,
+        ..KeyRules::default()
+    };
+    let mut computer = Computer::new(_input, rules, cache_dir).unwrap();
+    if let Some([_, path]) = checkpoint_files {
+        computer = computer.with_checkpoint(CheckpointFile::new(path));
+    }
+    let key = computer.get_key(n).unwrap();
     println!(
         "Using salt of {} index {} produces the {}th key",
-        computer.salt,
-        computer.keys.iter().nth(n - 1).unwrap().index,
-        n
+        computer.salt, key.index, n
     );
 }
 
+type DigestFilter = fn(&Digest) -> bool;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct KeyRules {
+    candidate_run: usize,
+    confirmation_run: usize,
+    window: u64,
+    stretch_rounds: usize,
+    algorithm: HashAlgorithm,
+}
+
+impl KeyRules {
+    const STRETCH_ROUNDS: usize = 2016;
+
+    fn validate(&self) -> Result<()> {
+        let nibbles = self.algorithm.digest_len() * 2;
+        if self.candidate_run == 0 || self.confirmation_run == 0 {
+            return Err(anyhow!("run lengths must be positive in {}", self));
+        }
+        if self.candidate_run > nibbles || self.confirmation_run > nibbles {
+            return Err(anyhow!(
+                "run lengths in {} exceed the {} nibbles of a {} digest",
+                self,
+                nibbles,
+                self.algorithm
+            ));
+        }
+        if self.window == 0 {
+            return Err(anyhow!("confirmation window must be positive in {}", self));
+        }
+        Ok(())
+    }
+}
+
+impl Default for KeyRules {
+    fn default() -> Self {
+        Self {
+            candidate_run: 3,
+            confirmation_run: 5,
+            window: 1000,
+            stretch_rounds: 0,
+            algorithm: HashAlgorithm::Md5,
+        }
+    }
+}
+
+impl Display for KeyRules {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}/{}/{}",
+            self.candidate_run,
+            self.confirmation_run,
+            self.window,
+            self.stretch_rounds,
+            self.algorithm
+        )
+    }
+}
+
+impl FromStr for KeyRules {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('/').collect();
+        if parts.len() != 5 {
+            return Err(anyhow!("invalid key rules '{}'", s));
+        }
+        let rules = Self {
+            candidate_run: parts[0].parse()?,
+            confirmation_run: parts[1].parse()?,
+            window: parts[2].parse()?,
+            stretch_rounds: parts[3].parse()?,
+            algorithm: parts[4].parse()?,
+        };
+        rules.validate()?;
+        Ok(rules)
+    }
+}
+
 struct Computer {
     salt: &'static str,
-    current_index: usize,
+    rules: KeyRules,
+    next_index: u64,
+    hashes: HashCache,
     keys: Vec<Key>,
+    confirmations: [u32; 16],
+    counted: Range<u64>,
+    checkpoint: Option<CheckpointFile>,
 }
 
 impl Computer {
-    pub(crate) fn new(salt: &'static str) -> Self {
-        Self {
+    pub(crate) fn new(
+        salt: &'static str,
+        rules: KeyRules,
+        cache_dir: Option<&str>,
+    ) -> Result<Self> {
+        rules.validate()?;
+        Ok(Self {
             salt,
-            current_index: 0,
+            next_index: 0,
+            hashes: HashCache::new(salt, &rules, cache_dir.map(Path::new)),
+            rules,
             keys: vec![],
+            confirmations: [0; 16],
+            counted: 0..0,
+            checkpoint: None,
+        })
+    }
+    pub(crate) fn with_checkpoint(mut self, file: CheckpointFile) -> Self {
+        if let Some(checkpoint) = file.load() {
+            if let Err(e) = self.resume(&checkpoint) {
+                warn!("not resuming from checkpoint: {}", e);
+            }
         }
+        self.checkpoint = Some(file);
+        self
     }
-    pub(crate) fn get_keys(&mut self, count: usize, stretch: bool) {
-        while self.keys.len() < count || self.keys.iter().take(count).any(|k| !k.resolved) {
-            self.next(stretch);
+    fn resume(&mut self, checkpoint: &Checkpoint) -> Result<()> {
+        let salt = checkpoint.get("salt")?;
+        let rules: KeyRules = checkpoint.parse("rules")?;
+        if salt != self.salt || rules != self.rules {
+            return Err(anyhow!(
+                "checkpoint is for salt '{}' with rules {}",
+                salt,
+                rules
+            ));
         }
+        let next_index: u64 = checkpoint.parse("next_index")?;
+        let keys = checkpoint
+            .get_all("key")
+            .map(|key| key.parse())
+            .collect::<Result<Vec<Key>>>()?;
+        self.keys = keys;
+        self.next_index = next_index;
+        self.hashes.advance_to(next_index);
+        println!(
+            "resuming from index {} with {} keys found",
+            next_index,
+            self.keys.len()
+        );
+        Ok(())
     }
-    fn next(&mut self, stretch: bool) {
-        let stream: Vec<char> = {
-            let s = format!("{}{}", self.salt, self.current_index);
-            if stretch {
-                stretched_md5(&s, 2016)
+    fn to_checkpoint(&self) -> Checkpoint {
+        let mut checkpoint = Checkpoint::default();
+        checkpoint
+            .push("salt", self.salt)
+            .push("rules", &self.rules)
+            .push("next_index", self.next_index);
+        for key in self.keys.iter() {
+            checkpoint.push("key", key);
+        }
+        checkpoint
+    }
+    fn save_checkpoint(&mut self, force: bool) {
+        if let Some(mut file) = self.checkpoint.take() {
+            let result = if force {
+                file.save(&self.to_checkpoint())
             } else {
-                md5(&s)
+                file.save_if_due(|| self.to_checkpoint())
+            };
+            if let Err(e) = result {
+                warn!("could not save checkpoint: {}", e);
             }
+            self.checkpoint = Some(file);
         }
-        .chars()
-        .collect();
-        let quintuples: Vec<_> = self
-            .keys
-            .iter()
-            .filter(|k| !k.resolved)
-            .map(|k| k.triplet)
-            .collect::<HashSet<_>>()
-            .into_iter()
-            .filter(|c| {
-                stream.windows(5).any(|chars| {
-                    chars[0] == *c
-                        && chars[0] == chars[1]
-                        && chars[1] == chars[2]
-                        && chars[2] == chars[3]
-                        && chars[3] == chars[4]
-                })
-            })
-            .collect();
-        for c in quintuples.into_iter() {
-            for key in self
-                .keys
-                .iter_mut()
-                .filter(|k| !k.resolved && k.triplet == c)
-            {
-                key.resolved = true;
-                // println!("key resolved: {:?} by {}", key, self.current_index);
+    }
+    pub(crate) fn get_key(&mut self, n: usize) -> Result<Key> {
+        if n == 0 {
+            return Err(anyhow!("keys are numbered from 1"));
+        }
+        while self.keys.len() < n {
+            self.next();
+        }
+        if let Some(file) = self.checkpoint.as_ref() {
+            if let Err(e) = file.remove() {
+                warn!("could not remove checkpoint: {}", e);
+            }
+        }
+        Ok(self.keys[n - 1])
+    }
+    fn count_confirmations(&mut self, index: u64, added: bool) {
+        let mask = self.hashes.get(index).confirmations;
+        for (nibble, count) in self.confirmations.iter_mut().enumerate() {
+            if mask & (1 << nibble) != 0 {
+                if added {
+                    *count += 1;
+                } else {
+                    *count -= 1;
+                }
+            }
+        }
+    }
+    fn check(&mut self, index: u64) -> Option<Key> {
+        let window = index + 1..index + 1 + self.rules.window;
+        if !self.counted.contains(&window.start) && self.counted.end != window.start {
+            self.confirmations = [0; 16];
+            self.counted = window.start..window.start;
+        }
+        while self.counted.start < window.start {
+            self.count_confirmations(self.counted.start, false);
+            self.counted.start += 1;
+        }
+        while self.counted.end < window.end {
+            self.count_confirmations(self.counted.end, true);
+            self.counted.end += 1;
+        }
+        let triplet = self.hashes.get(index).candidate?;
+        (self.confirmations[triplet as usize] > 0).then_some(Key { index, triplet })
+    }
+}
+
+impl Iterator for Computer {
+    type Item = Key;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let index = self.next_index;
+            let key = self.check(index);
+            self.next_index += 1;
+            self.hashes.advance_to(self.next_index);
+            if let Some(key) = key {
+                // println!("key found: {} by {}", index, triplet);
+                self.keys.push(key);
+            }
+            self.save_checkpoint(false);
+            if key.is_some() {
+                return key;
             }
         }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct HashInfo {
+    candidate: Option<u8>,
+    confirmations: u16,
+}
 
-        self.keys.retain(|k| {
-            let keep = k.resolved || self.current_index - k.index < 1000;
-            if !keep {
-                // println!("removing key {:?}", k);
+impl HashInfo {
+    fn new(digest: &Digest, rules: &KeyRules) -> Self {
+        let nibbles: Vec<u8> = (0..digest.nibble_count())
+            .map(|i| nibble(digest, i))
+            .collect();
+        let run_of = |len: usize| {
+            nibbles
+                .windows(len)
+                .filter(|w| w.iter().all(|&n| n == w[0]))
+                .map(|w| w[0])
+        };
+        Self {
+            candidate: run_of(rules.candidate_run).next(),
+            confirmations: run_of(rules.confirmation_run).fold(0, |mask, n| mask | 1 << n),
+        }
+    }
+}
+
+struct HashCache {
+    rules: KeyRules,
+    hashes: Miner<DigestFilter>,
+    window: VecDeque<HashInfo>,
+    window_start: u64,
+    disk: Option<DiskCache>,
+}
+
+impl HashCache {
+    const BATCH_SIZE: u64 = 1 << 10;
+
+    pub(crate) fn new(salt: &str, rules: &KeyRules, cache_dir: Option<&Path>) -> Self {
+        let disk = cache_dir.and_then(|dir| {
+            let path = match rules.algorithm {
+                HashAlgorithm::Md5 => dir.join(format!("{}_{}.bin", salt, rules.stretch_rounds)),
+                algorithm => dir.join(format!(
+                    "{}_{}_{}.bin",
+                    salt, rules.stretch_rounds, algorithm
+                )),
+            };
+            match DiskCache::open(&path, rules.algorithm) {
+                Ok(disk) => {
+                    debug!("{} digests cached in {}", disk.len, path.display());
+                    Some(disk)
+                }
+                Err(e) => {
+                    warn!("not using hash cache {}: {}", path.display(), e);
+                    None
+                }
             }
-            keep
         });
-
-        if let Some(c) = stream
-            .windows(3)
-            .filter(|chars| chars[0] == chars[1] && chars[1] == chars[2])
-            .map(|chars| chars[0])
-            .next()
-        {
-            self.keys.push(Key {
-                index: self.current_index,
-                triplet: c,
-                resolved: false,
-            })
+        Self {
+            rules: rules.clone(),
+            hashes: Miner::new(salt, (|_| true) as DigestFilter)
+                .with_batch_size(Self::BATCH_SIZE)
+                .with_stretch_rounds(rules.stretch_rounds)
+                .with_algorithm(rules.algorithm),
+            window: VecDeque::new(),
+            window_start: 0,
+            disk,
+        }
+    }
+    pub(crate) fn get(&mut self, index: u64) -> HashInfo {
+        assert!(
+            index >= self.window_start,
+            "index {} left the window",
+            index
+        );
+        while self.window_start + (self.window.len() as u64) <= index {
+            self.extend();
+        }
+        self.window[(index - self.window_start) as usize]
+    }
+    pub(crate) fn advance_to(&mut self, index: u64) {
+        while self.window_start < index {
+            if self.window.pop_front().is_none() {
+                self.window_start = index;
+                break;
+            }
+            self.window_start += 1;
         }
+    }
+    fn extend(&mut self) {
+        let index = self.window_start + self.window.len() as u64;
+        let cached = self.disk.as_mut().and_then(|disk| match disk.read(index) {
+            Ok(digest) => digest,
+            Err(e) => {
+                warn!("could not read hash cache: {}", e);
+                None
+            }
+        });
+        let digest = match cached {
+            Some(digest) => digest,
+            None => {
+                if self.hashes.resume_nonce() != index {
+                    self.hashes.seek(index);
+                }
+                let digest = self.hashes.next().unwrap().digest;
+                if let Some(disk) = self.disk.as_mut() {
+                    if let Err(e) = disk.append(index, &digest) {
+                        warn!("could not write hash cache: {}", e);
+                        self.disk = None;
+                    }
+                }
+                digest
+            }
+        };
+        self.window.push_back(HashInfo::new(&digest, &self.rules));
+    }
+}
+
+struct DiskCache {
+    file: File,
+    len: u64,
+    digest_len: u64,
+}
 
-        self.current_index += 1;
+impl DiskCache {
+    pub(crate) fn open(path: &Path, algorithm: HashAlgorithm) -> Result<Self> {
+        let digest_len = algorithm.digest_len() as u64;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let len = file.metadata()?.len() / digest_len;
+        file.set_len(len * digest_len)?;
+        Ok(Self {
+            file,
+            len,
+            digest_len,
+        })
+    }
+    pub(crate) fn read(&mut self, index: u64) -> Result<Option<Digest>> {
+        if index >= self.len {
+            return Ok(None);
+        }
+        let mut bytes = vec![0; self.digest_len as usize];
+        self.file.seek(SeekFrom::Start(index * self.digest_len))?;
+        self.file.read_exact(&mut bytes)?;
+        Ok(Some(Digest::new(&bytes)))
+    }
+    pub(crate) fn append(&mut self, index: u64, digest: &Digest) -> Result<()> {
+        if index != self.len {
+            return Ok(());
+        }
+        self.file.seek(SeekFrom::Start(index * self.digest_len))?;
+        self.file.write_all(digest.as_bytes())?;
+        self.len += 1;
+        Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 struct Key {
-    index: usize,
-    triplet: char,
-    resolved: bool,
+    index: u64,
+    triplet: u8,
 }
 
-fn md5(s: &str) -> String {
-    let digest = md5::compute(s);
-    format!("{:x}", digest)
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.index, hex_char(self.triplet))
+    }
 }
 
-fn stretched_md5(s: &str, times: usize) -> String {
-    let mut digest = md5::compute(s);
-    for _ in 0..times {
-        digest = md5::compute(format!("{:x}", digest));
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, triplet) = s.split_once(',').ok_or(anyhow!("invalid key '{}'", s))?;
+        Ok(Self {
+            index: index.parse()?,
+            triplet: u8::from_str_radix(triplet, 16)?,
+        })
     }
-    format!("{:x}", digest)
 }
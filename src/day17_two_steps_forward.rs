@@ -1,4 +1,8 @@
+use crate::grid::{Bounds, Heading, Point, Rect};
+use crate::md5_mining::{nibble, Hasher};
 use lazy_static::lazy_static;
+use rayon::prelude::*;
+use std::cell::RefCell;
 use std::collections::HashSet;
 use utils::a_star::{a_star_search, CurrentNodeDetails, Node, Successor};
 
@@ -9,18 +13,19 @@ pub(crate) fn run() {
     let _input = "udskfozm";
 
     let start = Path {
-        current_position: Coord { x: 0, y: 0 },
-        history: vec![],
+        current_position: Point::new(0, 0),
+        history: String::new(),
         passcode: _input,
     };
     let end = Path {
-        current_position: END_POSITION.clone(),
-        history: vec![],
+        current_position: *END_POSITION,
+        history: String::new(),
         passcode: _input,
     };
+    let hasher = RefCell::new(Hasher::new(_input, 0));
     let result = a_star_search(
         start,
-        get_successors,
+        |path| get_successors(path, &mut hasher.borrow_mut()),
         |current| distance_function(current, &end),
         |left| left.current_position == end.current_position,
         None,
@@ -29,20 +34,18 @@ pub(crate) fn run() {
     .shortest_path;
     println!(
         "the shortest path is {}",
-        result
-            .iter()
-            .last()
-            .unwrap()
-            .history
-            .iter()
-            .collect::<String>()
+        result.iter().last().unwrap().history
     );
 
     println!("the longest path is {}", get_longest_path(_input).len());
 }
 
 lazy_static! {
-    static ref END_POSITION: Coord = Coord { x: 3, y: 3 };
+    static ref END_POSITION: Point = Point::new(3, 3);
+    static ref VAULT: Rect = Rect {
+        min: Point::new(0, 0),
+        max: *END_POSITION,
+    };
 }
 
 fn get_longest_path(passcode: &'static str) -> String {
@@ -50,12 +53,20 @@ fn get_longest_path(passcode: &'static str) -> String {
     let mut finished_paths: HashSet<Path> = Default::default();
     paths.insert(Path {
         passcode,
-        current_position: Coord { x: 0, y: 0 },
-        history: vec![],
+        current_position: Point::new(0, 0),
+        history: String::new(),
     });
     while !paths.is_empty() {
+        let successors: Vec<Path> = paths
+            .par_iter()
+            .map_init(
+                || Hasher::new(passcode, 0),
+                |hasher, p| get_successors_internal(p, hasher).collect::<Vec<_>>(),
+            )
+            .flatten()
+            .collect();
         let mut new_paths = HashSet::new();
-        for successor in paths.iter().flat_map(|p| get_successors_internal(p)) {
+        for successor in successors {
             if successor.current_position == *END_POSITION {
                 finished_paths.insert(successor);
             } else {
@@ -70,42 +81,23 @@ fn get_longest_path(passcode: &'static str) -> String {
         .max_by(|a, b| a.history.len().cmp(&b.history.len()))
         .unwrap()
         .history
-        .into_iter()
-        .collect()
 }
 
-fn get_successors_internal(path: &Path) -> impl Iterator<Item = Path> + '_ {
-    let current = &path.current_position;
-    let is_open = |c: char| {
-        if c.is_numeric() || c == 'a' {
-            false
-        } else {
-            true
-        }
-    };
-    let hash = path.get_hash();
-    let mut chars = hash.chars();
-    [
-        (chars.next().unwrap(), current.y > 0, Direction::Up),
-        (
-            chars.next().unwrap(),
-            current.y < END_POSITION.y,
-            Direction::Down,
-        ),
-        (chars.next().unwrap(), current.x > 0, Direction::Left),
-        (
-            chars.next().unwrap(),
-            current.x < END_POSITION.x,
-            Direction::Right,
-        ),
-    ]
-    .into_iter()
-    .filter(move |(c, ok, _)| *ok && is_open(*c))
-    .map(|(_, _, dir)| path.moved(dir))
+fn get_successors_internal<'a>(
+    path: &'a Path,
+    hasher: &mut Hasher,
+) -> impl Iterator<Item = Path> + 'a {
+    let open = path.get_open_doors(hasher);
+    [Heading::North, Heading::South, Heading::West, Heading::East]
+        .into_iter()
+        .zip(open)
+        .filter(|&(heading, open)| open && VAULT.contains(&path.current_position.moved(heading, 1)))
+        .map(|(heading, _)| heading)
+        .map(|heading| path.moved(heading))
 }
 
-fn get_successors(path: &Path) -> Vec<Successor<Path, i32>> {
-    get_successors_internal(path)
+fn get_successors(path: &Path, hasher: &mut Hasher) -> Vec<Successor<Path, i32>> {
+    get_successors_internal(path, hasher)
         .map(|p| Successor::new(p, 1))
         .collect()
 }
@@ -114,71 +106,27 @@ fn distance_function(details: CurrentNodeDetails<Path, i32>, end: &Path) -> i32
     details
         .current_node
         .current_position
-        .get_manhattan_distance(&end.current_position) as i32
+        .manhattan_distance(&end.current_position) as i32
 }
 
 #[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Debug, Clone)]
 struct Path {
     passcode: &'static str,
-    current_position: Coord,
-    history: Vec<char>,
+    current_position: Point,
+    history: String,
 }
 
 impl Path {
-    pub(crate) fn get_hash(&self) -> String {
-        let s: String = self
-            .history
-            .iter()
-            .fold(self.passcode.to_string(), |mut hash, next| {
-                hash.push(*next);
-                hash
-            });
-        let digest = md5::compute(s);
-        format!("{:x}", digest)
+    pub(crate) fn get_open_doors(&self, hasher: &mut Hasher) -> [bool; 4] {
+        let digest = hasher.hash_suffix(self.history.as_bytes());
+        [0, 1, 2, 3].map(|i| nibble(&digest, i) > 0xa)
     }
-    pub(crate) fn moved(&self, direction: Direction) -> Self {
+    pub(crate) fn moved(&self, heading: Heading) -> Self {
         let mut result = self.clone();
-        match direction {
-            Direction::Up => {
-                result.current_position.y -= 1;
-                result.history.push('U');
-            }
-            Direction::Down => {
-                result.current_position.y += 1;
-                result.history.push('D');
-            }
-            Direction::Left => {
-                result.current_position.x -= 1;
-                result.history.push('L');
-            }
-            Direction::Right => {
-                result.current_position.x += 1;
-                result.history.push('R');
-            }
-        }
-
+        result.current_position = result.current_position.moved(heading, 1);
+        result.history.push(heading.as_udlr());
         result
     }
 }
 
-#[derive(Copy, Clone)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
 impl Node for Path {}
-
-#[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Debug)]
-struct Coord {
-    x: usize,
-    y: usize,
-}
-
-impl Coord {
-    pub(crate) fn get_manhattan_distance(&self, other: &Self) -> usize {
-        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
-    }
-}
@@ -1,10 +1,11 @@
+use crate::grid::{Bounds, Point};
 use anyhow::{anyhow, Result};
 use log::debug;
 use permutator::Permutation;
 use rayon::prelude::*;
 use std::collections::BTreeMap;
 use std::str::FromStr;
-use utils::a_star::{a_star_search, CurrentNodeDetails, Node, Options, Successor};
+use utils::a_star::{a_star_search, CurrentNodeDetails, Options, Successor};
 
 pub(crate) fn run() {
     let _input = "###########
@@ -47,12 +48,12 @@ fn find_shortest_path_for(system: &DuctSystem, points: &[u32], return_to_origin:
 }
 
 fn find_shortest_path_for_points(system: &DuctSystem, from: u32, to: u32) -> usize {
-    let start = system.state.positions.get(&Position(from)).unwrap().clone();
+    let start = *system.state.positions.get(&Position(from)).unwrap();
     let end = system.state.positions.get(&Position(to)).unwrap();
     let options = Options::default().with_no_logs();
     let results = a_star_search(
         start,
-        |current: &Coord| get_successors(current, &system.duct),
+        |current: &Point| get_successors(current, &system.duct),
         |current| distance_function(current, end),
         |left| left == end,
         Some(&options),
@@ -61,33 +62,18 @@ fn find_shortest_path_for_points(system: &DuctSystem, from: u32, to: u32) -> usi
     results.shortest_path_cost as usize
 }
 
-fn get_successors(current: &Coord, duct: &Duct) -> Vec<Successor<Coord, i32>> {
-    let mut results = Vec::new();
-    let mut push = |x: usize, y: usize| {
-        results.push(Successor::new(Coord { x, y }, 1));
-    };
-    if current.x > 0 && !duct.is_wall(current.x - 1, current.y) {
-        push(current.x - 1, current.y)
-    }
-    if current.x < duct.len_x() - 1 && !duct.is_wall(current.x + 1, current.y) {
-        push(current.x + 1, current.y)
-    }
-    if current.y > 0 && !duct.is_wall(current.x, current.y - 1) {
-        push(current.x, current.y - 1)
-    }
-    if current.y < duct.len_y() - 1 && !duct.is_wall(current.x, current.y + 1) {
-        push(current.x, current.y + 1)
-    }
-
-    results
+fn get_successors(current: &Point, duct: &Duct) -> Vec<Successor<Point, i32>> {
+    current
+        .neighbors()
+        .filter(|next| duct.contains(next))
+        .map(|next| Successor::new(next, 1))
+        .collect()
 }
 
-fn distance_function(details: CurrentNodeDetails<Coord, i32>, end: &Coord) -> i32 {
+fn distance_function(details: CurrentNodeDetails<Point, i32>, end: &Point) -> i32 {
     details.current_node.manhattan_distance(end) as i32
 }
 
-impl Node for Coord {}
-
 struct Duct(Vec<Vec<Space>>);
 
 impl Duct {
@@ -108,9 +94,17 @@ impl Duct {
     }
 }
 
+impl Bounds for Duct {
+    fn contains(&self, point: &Point) -> bool {
+        (0..self.len_x() as i64).contains(&point.x)
+            && (0..self.len_y() as i64).contains(&point.y)
+            && !self.is_wall(point.x as usize, point.y as usize)
+    }
+}
+
 #[derive(Default)]
 struct DuctState {
-    positions: BTreeMap<Position, Coord>,
+    positions: BTreeMap<Position, Point>,
 }
 
 struct DuctSystem {
@@ -126,18 +120,6 @@ enum Space {
     Wall,
 }
 
-#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Debug, Clone)]
-struct Coord {
-    x: usize,
-    y: usize,
-}
-
-impl Coord {
-    pub(crate) fn manhattan_distance(&self, other: &Self) -> usize {
-        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
-    }
-}
-
 impl FromStr for DuctSystem {
     type Err = anyhow::Error;
 
@@ -159,7 +141,7 @@ impl FromStr for DuctSystem {
                                             Position(
                                                 c.to_digit(10).ok_or(anyhow!("parse int error"))?,
                                             ),
-                                            Coord { x, y },
+                                            Point::new(x as i64, y as i64),
                                         );
                                     }
                                     Space::Empty
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+use utils::a_star::Node;
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum Heading {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Heading {
+    pub(crate) const ALL: [Heading; 4] = [Self::North, Self::East, Self::South, Self::West];
+
+    pub(crate) fn turn(&self, turn: Turn) -> Self {
+        let index = Self::ALL.iter().position(|h| h == self).unwrap();
+        let offset = match turn {
+            Turn::Right => 1,
+            Turn::Left => 3,
+        };
+        Self::ALL[(index + offset) % Self::ALL.len()]
+    }
+    pub(crate) fn offset(&self) -> (i64, i64) {
+        match self {
+            Heading::North => (0, -1),
+            Heading::East => (1, 0),
+            Heading::South => (0, 1),
+            Heading::West => (-1, 0),
+        }
+    }
+    pub(crate) fn as_udlr(&self) -> char {
+        match self {
+            Heading::North => 'U',
+            Heading::East => 'R',
+            Heading::South => 'D',
+            Heading::West => 'L',
+        }
+    }
+}
+
+impl TryFrom<char> for Heading {
+    type Error = anyhow::Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        Ok(match c {
+            'N' | 'U' | '^' => Self::North,
+            'E' | 'R' | '>' => Self::East,
+            'S' | 'D' | 'v' => Self::South,
+            'W' | 'L' | '<' => Self::West,
+            _ => return Err(anyhow::anyhow!("invalid heading '{}'", c)),
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Turn {
+    Left,
+    Right,
+}
+
+impl TryFrom<char> for Turn {
+    type Error = anyhow::Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        Ok(match c {
+            'L' => Self::Left,
+            'R' => Self::Right,
+            _ => return Err(anyhow::anyhow!("invalid turn '{}'", c)),
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) struct Point {
+    pub(crate) x: i64,
+    pub(crate) y: i64,
+}
+
+impl Point {
+    pub(crate) fn new(x: i64, y: i64) -> Self {
+        Self { x, y }
+    }
+    pub(crate) fn moved(&self, heading: Heading, steps: i64) -> Self {
+        let (dx, dy) = heading.offset();
+        Self {
+            x: self.x + dx * steps,
+            y: self.y + dy * steps,
+        }
+    }
+    pub(crate) fn neighbors(&self) -> impl Iterator<Item = Point> + '_ {
+        Heading::ALL.iter().map(move |&h| self.moved(h, 1))
+    }
+    pub(crate) fn manhattan_distance(&self, other: &Self) -> u64 {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+}
+
+impl Node for Point {}
+
+pub(crate) trait Bounds {
+    fn contains(&self, point: &Point) -> bool;
+}
+
+pub(crate) struct Unbounded;
+
+impl Bounds for Unbounded {
+    fn contains(&self, _point: &Point) -> bool {
+        true
+    }
+}
+
+pub(crate) struct Rect {
+    pub(crate) min: Point,
+    pub(crate) max: Point,
+}
+
+impl Bounds for Rect {
+    fn contains(&self, point: &Point) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+    }
+}
+
+pub(crate) struct Walker<B: Bounds = Unbounded> {
+    bounds: B,
+    heading: Heading,
+    position: Point,
+    path: Vec<Point>,
+    visited: HashSet<Point>,
+    first_revisit: Option<Point>,
+}
+
+impl Walker<Unbounded> {
+    pub(crate) fn unbounded(start: Point, heading: Heading) -> Self {
+        Self::new(start, heading, Unbounded)
+    }
+}
+
+impl<B: Bounds> Walker<B> {
+    pub(crate) fn new(start: Point, heading: Heading, bounds: B) -> Self {
+        Self {
+            bounds,
+            heading,
+            position: start,
+            path: vec![start],
+            visited: [start].into_iter().collect(),
+            first_revisit: None,
+        }
+    }
+    pub(crate) fn position(&self) -> Point {
+        self.position
+    }
+    pub(crate) fn start(&self) -> Point {
+        self.path[0]
+    }
+    pub(crate) fn path(&self) -> &[Point] {
+        &self.path[..]
+    }
+    pub(crate) fn first_revisit(&self) -> Option<Point> {
+        self.first_revisit
+    }
+    pub(crate) fn turn(&mut self, turn: Turn) {
+        self.heading = self.heading.turn(turn);
+    }
+    pub(crate) fn forward(&mut self, steps: usize) -> usize {
+        self.walk(self.heading, steps)
+    }
+    pub(crate) fn walk(&mut self, heading: Heading, steps: usize) -> usize {
+        for taken in 0..steps {
+            let next = self.position.moved(heading, 1);
+            if !self.bounds.contains(&next) {
+                return taken;
+            }
+            self.visit(next);
+        }
+        steps
+    }
+    fn visit(&mut self, point: Point) {
+        if !self.visited.insert(point) && self.first_revisit.is_none() {
+            self.first_revisit = Some(point);
+        }
+        self.position = point;
+        self.path.push(point);
+    }
+}
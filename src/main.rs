@@ -1,5 +1,7 @@
 use utils::timer::Timer;
 
+mod checkpoint;
+mod cipher;
 mod day01_no_time_for_taxicab;
 mod day02_bathroom_security;
 mod day03_squares_with_three_sides;
@@ -25,6 +27,9 @@ mod day22_grid_computing;
 mod day23_safe_cracking;
 mod day24_air_duct_spelunking;
 mod day25_clock_signal;
+mod grid;
+mod md5_mining;
+mod palindrome;
 
 fn main() {
     env_logger::init();
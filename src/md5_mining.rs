@@ -0,0 +1,251 @@
+use log::debug;
+use rayon::prelude::*;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum HashAlgorithm {
+    Md5,
+    Sha1,
+}
+
+impl HashAlgorithm {
+    pub(crate) fn digest_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Md5 => 16,
+            HashAlgorithm::Sha1 => 20,
+        }
+    }
+    fn compute(&self, data: &[u8]) -> Digest {
+        match self {
+            HashAlgorithm::Md5 => Digest::new(&md5::compute(data).0),
+            HashAlgorithm::Sha1 => Digest::new(&sha1_smol::Sha1::from(data).digest().bytes()),
+        }
+    }
+}
+
+impl Display for HashAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashAlgorithm::Md5 => write!(f, "md5"),
+            HashAlgorithm::Sha1 => write!(f, "sha1"),
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "md5" => Self::Md5,
+            "sha1" => Self::Sha1,
+            _ => return Err(anyhow::anyhow!("unknown hash algorithm '{}'", s)),
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Digest {
+    bytes: [u8; 20],
+    len: usize,
+}
+
+impl Digest {
+    pub(crate) fn new(bytes: &[u8]) -> Self {
+        let mut digest = Self {
+            bytes: [0; 20],
+            len: bytes.len(),
+        };
+        digest.bytes[..bytes.len()].copy_from_slice(bytes);
+        digest
+    }
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+    pub(crate) fn nibble_count(&self) -> usize {
+        2 * self.len
+    }
+}
+
+pub(crate) fn nibble(digest: &Digest, index: usize) -> u8 {
+    let byte = digest.as_bytes()[index / 2];
+    if index % 2 == 0 {
+        byte >> 4
+    } else {
+        byte & 0x0f
+    }
+}
+
+pub(crate) fn leading_zero_nibbles(digest: &Digest) -> usize {
+    (0..digest.nibble_count())
+        .take_while(|&i| nibble(digest, i) == 0)
+        .count()
+}
+
+pub(crate) fn hex_char(nibble: u8) -> char {
+    char::from_digit(nibble as u32, 16).unwrap()
+}
+
+fn write_hex(digest: &Digest, out: &mut [u8; 40]) -> usize {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    for (i, byte) in digest.as_bytes().iter().enumerate() {
+        out[2 * i] = HEX[(byte >> 4) as usize];
+        out[2 * i + 1] = HEX[(byte & 0x0f) as usize];
+    }
+    digest.nibble_count()
+}
+
+pub(crate) struct Hasher {
+    buffer: Vec<u8>,
+    prefix_len: usize,
+    hex: [u8; 40],
+    rounds: usize,
+    algorithm: HashAlgorithm,
+}
+
+impl Hasher {
+    pub(crate) fn new(prefix: &str, rounds: usize) -> Self {
+        Self::with_algorithm(prefix, rounds, HashAlgorithm::Md5)
+    }
+    pub(crate) fn with_algorithm(prefix: &str, rounds: usize, algorithm: HashAlgorithm) -> Self {
+        Self {
+            buffer: prefix.as_bytes().to_vec(),
+            prefix_len: prefix.len(),
+            hex: [0; 40],
+            rounds,
+            algorithm,
+        }
+    }
+    pub(crate) fn hash_nonce(&mut self, nonce: u64) -> Digest {
+        self.buffer.truncate(self.prefix_len);
+        let mut nonce_buffer = [0u8; 20];
+        let mut start = nonce_buffer.len();
+        let mut remaining = nonce;
+        loop {
+            start -= 1;
+            nonce_buffer[start] = b'0' + (remaining % 10) as u8;
+            remaining /= 10;
+            if remaining == 0 {
+                break;
+            }
+        }
+        self.buffer.extend_from_slice(&nonce_buffer[start..]);
+        self.finish()
+    }
+    pub(crate) fn hash_suffix(&mut self, suffix: &[u8]) -> Digest {
+        self.buffer.truncate(self.prefix_len);
+        self.buffer.extend_from_slice(suffix);
+        self.finish()
+    }
+    fn finish(&mut self) -> Digest {
+        let mut digest = self.algorithm.compute(&self.buffer);
+        for _ in 0..self.rounds {
+            let len = write_hex(&digest, &mut self.hex);
+            digest = self.algorithm.compute(&self.hex[..len]);
+        }
+        digest
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Hit {
+    pub(crate) nonce: u64,
+    pub(crate) digest: Digest,
+}
+
+pub(crate) struct Miner<F> {
+    prefix: String,
+    next_nonce: u64,
+    batch_size: u64,
+    rounds: usize,
+    algorithm: HashAlgorithm,
+    filter: F,
+    hits: VecDeque<Hit>,
+}
+
+impl<F: Fn(&Digest) -> bool + Sync> Miner<F> {
+    const DEFAULT_BATCH_SIZE: u64 = 1 << 14;
+
+    pub(crate) fn new(prefix: &str, filter: F) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            next_nonce: 0,
+            batch_size: Self::DEFAULT_BATCH_SIZE,
+            rounds: 0,
+            algorithm: HashAlgorithm::Md5,
+            filter,
+            hits: VecDeque::new(),
+        }
+    }
+    pub(crate) fn with_batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    pub(crate) fn with_stretch_rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+        self
+    }
+    pub(crate) fn with_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+    pub(crate) fn seek(&mut self, nonce: u64) {
+        self.next_nonce = nonce;
+        self.hits.clear();
+    }
+    pub(crate) fn resume_nonce(&self) -> u64 {
+        self.hits
+            .front()
+            .map(|hit| hit.nonce)
+            .unwrap_or(self.next_nonce)
+    }
+    pub(crate) fn try_next(&mut self) -> Option<Hit> {
+        if self.hits.is_empty() {
+            self.mine_batch();
+        }
+        self.hits.pop_front()
+    }
+    fn mine_batch(&mut self) {
+        let start = self.next_nonce;
+        let end = start + self.batch_size;
+        let filter = &self.filter;
+        let hits: Vec<Hit> = (start..end)
+            .into_par_iter()
+            .map_init(
+                || Hasher::with_algorithm(&self.prefix, self.rounds, self.algorithm),
+                |hasher, nonce| {
+                    let digest = hasher.hash_nonce(nonce);
+                    if filter(&digest) {
+                        Some(Hit { nonce, digest })
+                    } else {
+                        None
+                    }
+                },
+            )
+            .flatten()
+            .collect();
+        debug!(
+            "mined nonces {}..{} of '{}': {} hits",
+            start,
+            end,
+            self.prefix,
+            hits.len()
+        );
+        self.hits.extend(hits);
+        self.next_nonce = end;
+    }
+}
+
+impl<F: Fn(&Digest) -> bool + Sync> Iterator for Miner<F> {
+    type Item = Hit;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(hit) = self.try_next() {
+                return Some(hit);
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct PalindromePattern {
+    template: Vec<char>,
+}
+
+impl PalindromePattern {
+    pub(crate) fn len(&self) -> usize {
+        self.template.len()
+    }
+    pub(crate) fn matches(&self, window: &[char]) -> bool {
+        window.len() == self.len()
+            && (0..self.len()).all(|i| {
+                (i + 1..self.len())
+                    .all(|j| (self.template[i] == self.template[j]) == (window[i] == window[j]))
+            })
+    }
+    pub(crate) fn find<'a>(&'a self, chars: &'a [char]) -> impl Iterator<Item = usize> + 'a {
+        chars
+            .windows(self.len())
+            .enumerate()
+            .filter(|(_, window)| self.matches(window))
+            .map(|(i, _)| i)
+    }
+}
+
+fn half(chars: &[char]) -> &[char] {
+    &chars[..chars.len().div_ceil(2)]
+}
+
+pub(crate) fn is_complement(a: &[char], b: &[char]) -> bool {
+    half(a).iter().rev().eq(half(b).iter())
+}
+
+impl Display for PalindromePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.template.iter().collect::<String>())
+    }
+}
+
+impl FromStr for PalindromePattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let template: Vec<char> = s.chars().collect();
+        if template.len() < 2 || !template.iter().all(|c| c.is_ascii_lowercase()) {
+            return Err(anyhow!("invalid pattern template '{}'", s));
+        }
+        if !template.iter().eq(template.iter().rev()) {
+            return Err(anyhow!("pattern template '{}' is not a palindrome", s));
+        }
+        Ok(Self { template })
+    }
+}